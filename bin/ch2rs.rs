@@ -1,10 +1,172 @@
-use anyhow::Result;
+use std::{env, fs, path::Path, time::Duration};
+
+use anyhow::{bail, Context, Result};
 use structopt::StructOpt;
 
+const CONFIG: &str = "ch2rs.toml";
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
-    let options = ch2rs::Options::from_args();
+    let mut args = env::args_os().collect::<Vec<_>>();
+
+    if args.len() == 1 {
+        return generate_config(Path::new(CONFIG)).await;
+    }
+
+    if args.get(1).is_some_and(|arg| arg == "dump") {
+        args.remove(1);
+        args[0] = "ch2rs dump".into();
+        return dump(ch2rs::DumpOptions::from_iter(args)).await;
+    }
+
+    if args.get(1).is_some_and(|arg| arg == "regen") {
+        args.remove(1);
+        args[0] = "ch2rs regen".into();
+        return regen(ch2rs::RegenOptions::from_iter(args)).await;
+    }
+
+    if args.get(1).is_some_and(|arg| arg == "diff") {
+        args.remove(1);
+        args[0] = "ch2rs diff".into();
+        return diff(ch2rs::DiffOptions::from_iter(args)).await;
+    }
+
+    let options = ch2rs::Options::from_iter(args);
+
+    if options.watch {
+        return watch(options).await;
+    }
+
+    // Without the table, the file is checked using options from its header.
+    if let (Some(path), false) = (&options.check, has_target(&options)) {
+        let connection = options.connection.resolve()?;
+        return ch2rs::check_file(path, &connection).await;
+    }
+
+    let check = options.check.clone();
+    let output = options.output_path()?;
     let code = ch2rs::generate(options).await?;
-    println!("{}", code);
+
+    if let Some(path) = check {
+        ch2rs::check(&path, &code)?;
+    } else if let Some(path) = output {
+        let status = ch2rs::write_code(&path, &code)?;
+        println!("{} {}", status, path.display());
+    } else {
+        println!("{}", code);
+    }
+
+    Ok(())
+}
+
+async fn dump(options: ch2rs::DumpOptions) -> Result<()> {
+    let json = ch2rs::dump(&options).await?.to_json();
+
+    match &options.output {
+        Some(path) => {
+            fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))?
+        }
+        None => print!("{}", json),
+    }
+
+    Ok(())
+}
+
+async fn regen(options: ch2rs::RegenOptions) -> Result<()> {
+    let connection = options.connection.resolve()?;
+
+    for path in &options.files {
+        let status = ch2rs::regen(path, &connection)
+            .await
+            .with_context(|| format!("failed to regenerate {}", path.display()))?;
+
+        println!("{} {}", status, path.display());
+    }
+
+    Ok(())
+}
+
+async fn diff(options: ch2rs::DiffOptions) -> Result<()> {
+    let changes = ch2rs::diff(&options).await?;
+
+    if changes.is_empty() {
+        println!("no changes");
+    }
+
+    for change in &changes {
+        println!("{}: {}", change, change.compatibility);
+    }
+
+    let breaking = changes
+        .iter()
+        .filter(|c| c.compatibility.is_breaking())
+        .count();
+
+    if breaking > 0 {
+        bail!("{} of {} changes are breaking", breaking, changes.len());
+    }
+
+    Ok(())
+}
+
+async fn generate_config(path: &Path) -> Result<()> {
+    for target in read_targets(path)? {
+        let status = ch2rs::generate_target(&target)
+            .await
+            .with_context(|| format!("failed to generate {}", target.output.display()))?;
+
+        println!("{} {}", status, target.output.display());
+    }
+
     Ok(())
 }
+
+fn read_targets(path: &Path) -> Result<Vec<ch2rs::Target>> {
+    if !path.exists() {
+        bail!(
+            "there is no {} in the current directory, see `ch2rs --help`",
+            CONFIG
+        );
+    }
+
+    let mut config = ch2rs::Config::read(path)?;
+    config.connection = config.connection.resolve()?;
+    config.targets()
+}
+
+fn has_target(options: &ch2rs::Options) -> bool {
+    !options.table.is_empty() || options.query.is_some() || options.query_file.is_some()
+}
+
+async fn watch(mut options: ch2rs::Options) -> Result<()> {
+    let interval = Duration::from_secs(options.interval);
+
+    let targets = if !has_target(&options) {
+        read_targets(Path::new(CONFIG))?
+    } else {
+        let output = options
+            .output_path()?
+            .context("`--watch` requires `-o` or `--out-dir`")?;
+        options.connection = options.connection.resolve()?;
+        vec![ch2rs::Target { output, options }]
+    };
+
+    let mut watcher = ch2rs::Watcher::new(targets)?;
+    let mut first = true;
+
+    loop {
+        for (target, status) in watcher.poll().await {
+            let path = target.output.display();
+
+            match status {
+                // Only changes are reported after the first poll.
+                Ok(ch2rs::WriteStatus::Unchanged) if !first => {}
+                Ok(status) => println!("{} {}", status, path),
+                Err(err) => eprintln!("failed to generate {}: {:#}", path, err),
+            }
+        }
+
+        first = false;
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -0,0 +1,171 @@
+//! Compile-time generation of rows for the `clickhouse` crate.
+//!
+//! ```ignore
+//! ch2rs_macros::table!(
+//!     path = "schema/events.sql",
+//!     table = "events",
+//!     deserialize,
+//!     owned,
+//! );
+//! ```
+//!
+//! The macro reads `CREATE TABLE` statements from the file (relative to the
+//! crate's root) and expands to the same items `ch2rs` generates, so no
+//! ClickHouse server is required to build the crate.
+//!
+//! Supported arguments:
+//! * `path = "..."`, required
+//! * `table = "..."`, required
+//! * `database = "..."`
+//! * `serialize`, `deserialize`, `owned`
+//! * `string_repr = "str|string|cow"`
+//! * `type = "SqlType=RustType"`, `override = "column=RustType"`
+//! * `bytes = "column"`, `ignore = "column"`, `borrow = "column"`, `own = "column"`
+//! * `derive = "Trait"`
+//!
+//! All arguments except flags can be repeated like the CLI options.
+
+use std::{env, fs, path::PathBuf};
+
+use proc_macro::TokenStream;
+use quote::quote;
+use structopt::StructOpt;
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Ident, LitStr, Token,
+};
+
+struct Arg {
+    key: Ident,
+    value: Option<LitStr>,
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let key = Ident::parse_any(input)?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { key, value })
+    }
+}
+
+struct Args(Punctuated<Arg, Token![,]>);
+
+impl Parse for Args {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        Punctuated::parse_terminated(input).map(Self)
+    }
+}
+
+struct Input {
+    path: LitStr,
+    args: Vec<String>,
+}
+
+fn make_input(args: Args) -> syn::Result<Input> {
+    let mut path = None;
+    let mut table = None;
+    let mut cli = Vec::new();
+
+    for arg in args.0 {
+        let key = arg.key.unraw().to_string();
+
+        let flag = match key.as_str() {
+            "serialize" => "-S",
+            "deserialize" => "-D",
+            "owned" => "--owned",
+            "path" | "table" | "database" | "string_repr" | "type" | "override" | "bytes"
+            | "ignore" | "borrow" | "own" | "derive" => "",
+            _ => return Err(syn::Error::new(arg.key.span(), "unknown argument")),
+        };
+
+        if !flag.is_empty() {
+            if let Some(value) = arg.value {
+                return Err(syn::Error::new(value.span(), "the flag takes no value"));
+            }
+
+            cli.push(flag.into());
+            continue;
+        }
+
+        let Some(value) = arg.value else {
+            return Err(syn::Error::new(arg.key.span(), "expected `= \"...\"`"));
+        };
+
+        let option = match key.as_str() {
+            "path" => {
+                path = Some(value);
+                continue;
+            }
+            "table" => {
+                table = Some(value.value());
+                continue;
+            }
+            "database" => "-d",
+            "string_repr" => "--string-repr",
+            "type" => "-T",
+            "override" => "-O",
+            "bytes" => "-B",
+            "ignore" => "-I",
+            "borrow" => "--borrow",
+            "own" => "--own",
+            "derive" => "--derive",
+            _ => unreachable!(),
+        };
+
+        cli.push(option.into());
+        cli.push(value.value());
+    }
+
+    let span = proc_macro2::Span::call_site();
+    let path = path.ok_or_else(|| syn::Error::new(span, "`path` is required"))?;
+    let table = table.ok_or_else(|| syn::Error::new(span, "`table` is required"))?;
+
+    let mut args = vec!["ch2rs".into(), table];
+    args.extend(cli);
+
+    Ok(Input { path, args })
+}
+
+fn expand(input: Input) -> syn::Result<proc_macro2::TokenStream> {
+    let error = |err: &dyn std::fmt::Display| syn::Error::new(input.path.span(), err);
+
+    let options = ch2rs::Options::from_iter_safe(&input.args).map_err(|err| error(&err))?;
+
+    let root = env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from);
+    let path = root.join(input.path.value());
+    let sql = fs::read_to_string(&path)
+        .map_err(|err| error(&format!("failed to read {}: {}", path.display(), err)))?;
+
+    let items = ch2rs::__private::generate_items_from_ddl(&sql, &options)
+        .map_err(|err| error(&format!("{:#}", err)))?;
+
+    // Rebuild the crate if the schema is changed.
+    let path = path.to_string_lossy();
+
+    Ok(quote! {
+        const _: &str = include_str!(#path);
+        #items
+    })
+}
+
+/// Generates a row and enums from a `CREATE TABLE` statement in the file.
+///
+/// See the crate's documentation for supported arguments.
+#[proc_macro]
+pub fn table(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as Args);
+
+    make_input(args)
+        .and_then(expand)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,16 @@
+-- Schema of the test tables.
+
+CREATE DATABASE IF NOT EXISTS analytics;
+
+CREATE TABLE analytics.events
+(
+    `timestamp` DateTime COMMENT 'when the event happened',
+    kind        Enum8('click' = 1, 'view' = 2),
+    user        String,
+    tags        Array(LowCardinality(String)),
+    `type`      Nullable(UInt32),
+    ignored     UInt8,
+    INDEX kind_idx kind TYPE set(0) GRANULARITY 1
+)
+ENGINE = MergeTree
+ORDER BY (kind, timestamp);
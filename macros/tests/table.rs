@@ -0,0 +1,28 @@
+mod events {
+    ch2rs_macros::table!(
+        path = "tests/schema/events.sql",
+        table = "events",
+        serialize,
+        deserialize,
+        owned,
+        type = "DateTime=u32",
+        ignore = "ignored",
+        derive = "Clone",
+        derive = "PartialEq",
+    );
+}
+
+#[test]
+fn expands_to_row() {
+    let row = events::Row {
+        timestamp: 1_700_000_000,
+        kind: events::Kind::View,
+        user: "somebody".into(),
+        tags: vec!["foo".into()],
+        r#type: None,
+    };
+
+    assert_eq!(row.clone(), row);
+    assert_eq!(events::Row::TABLE, "events");
+    assert_eq!(events::Row::DATABASE, "analytics");
+}
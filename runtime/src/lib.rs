@@ -0,0 +1,192 @@
+//! Runtime support for rows generated by `ch2rs`.
+//!
+//! Generated code calls it instead of carrying its own copy, e.g.
+//! `verify_schema()` generated by `--verify-schema`:
+//!
+//! ```ignore
+//! Row::verify_schema(&client).await?;
+//! ```
+//!
+//! Only [`SchemaMismatch`] is a public API, functions called by generated
+//! code are in the hidden `__private` module.
+
+use std::{error::Error, fmt};
+
+/// An error of `verify_schema()`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SchemaMismatch {
+    Fetch(clickhouse::error::Error),
+    /// The table and problems, every problem describes one column.
+    Columns(String, Vec<String>),
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch(err) => write!(f, "failed to fetch columns: {}", err),
+            Self::Columns(table, problems) => {
+                let problems = problems.join("; ");
+                write!(f, "`{}` doesn't match the row: {}", table, problems)
+            }
+        }
+    }
+}
+
+impl Error for SchemaMismatch {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Fetch(err) => Some(err),
+            Self::Columns(..) => None,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Called by code generated by `ch2rs`, not a public API.
+
+    use super::SchemaMismatch;
+
+    /// Compares columns of the table on the server with expected names and types,
+    /// ignored columns may be present or absent.
+    pub async fn verify_schema(
+        client: &clickhouse::Client,
+        database: &str,
+        table: &str,
+        expected: &[(&str, &str)],
+        ignored: &[&str],
+    ) -> Result<(), SchemaMismatch> {
+        let actual = client
+            .query("SELECT name, type FROM system.columns WHERE database = ? AND table = ? ORDER BY position")
+            .bind(database)
+            .bind(table)
+            .fetch_all::<(String, String)>()
+            .await
+            .map_err(SchemaMismatch::Fetch)?;
+
+        let problems = verify_columns(expected, ignored, &actual);
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let table = format!("{}.{}", database, table);
+        Err(SchemaMismatch::Columns(table, problems))
+    }
+
+    /// Returns missing, extra, retyped and reordered columns, one problem per line.
+    pub fn verify_columns(
+        expected: &[(&str, &str)],
+        ignored: &[&str],
+        actual: &[(String, String)],
+    ) -> Vec<String> {
+        let mut actual = actual.to_vec();
+        actual.retain(|(name, _)| !ignored.contains(&name.as_str()));
+
+        let expected = expected
+            .iter()
+            .filter(|(name, _)| !ignored.contains(name))
+            .collect::<Vec<_>>();
+
+        let mut problems = Vec::new();
+
+        for (name, type_) in &expected {
+            let found = actual.iter().find(|(n, _)| n == name);
+            let problem = match found.map(|(_, t)| normalize_type(t)) {
+                None => format!("`{}` is missing", name),
+                Some(t) if t != *type_ => format!("`{}` is `{}`, not `{}`", name, t, type_),
+                Some(_) => continue,
+            };
+            problems.push(problem);
+        }
+
+        for (name, _) in &actual {
+            if !expected.iter().any(|(n, _)| n == name) {
+                problems.push(format!("`{}` is extra", name));
+            }
+        }
+
+        let is_common = |name: &str| {
+            let in_expected = expected.iter().any(|(n, _)| *n == name);
+            in_expected && actual.iter().any(|(n, _)| n == name)
+        };
+        let expected_order = expected.iter().map(|(n, _)| *n);
+        let expected_order = expected_order.filter(|n| is_common(n));
+        let actual_order = actual.iter().map(|(n, _)| n.as_str());
+        let actual_order = actual_order.filter(|n| is_common(n));
+
+        if !expected_order.clone().eq(actual_order.clone()) {
+            let expected = expected_order.collect::<Vec<_>>().join(", ");
+            let actual = actual_order.collect::<Vec<_>>().join(", ");
+            problems.push(format!("columns are `{}`, not `{}`", actual, expected));
+        }
+
+        problems
+    }
+
+    /// Removes wrappers that don't affect the representation and names of
+    /// tuple elements, e.g. `LowCardinality(String)` becomes `String`.
+    pub fn normalize_type(raw: &str) -> String {
+        let raw = raw.trim();
+        let inner = |wrapper: &str| {
+            raw.strip_prefix(wrapper)
+                .and_then(|rest| rest.strip_prefix('('))
+                .and_then(|rest| rest.strip_suffix(')'))
+        };
+
+        if let Some(inner) = inner("LowCardinality") {
+            return normalize_type(inner);
+        }
+
+        if let Some(inner) = inner("SimpleAggregateFunction") {
+            return split_args(inner)
+                .last()
+                .map_or_else(String::new, |t| normalize_type(t));
+        }
+
+        for wrapper in ["Nullable", "Array", "Map", "Tuple"] {
+            if let Some(inner) = inner(wrapper) {
+                let args = split_args(inner)
+                    .into_iter()
+                    .map(|arg| match arg.split_once(' ') {
+                        Some((name, type_)) if wrapper == "Tuple" && !name.contains('(') => {
+                            normalize_type(type_)
+                        }
+                        _ => normalize_type(arg),
+                    })
+                    .collect::<Vec<_>>();
+
+                return format!("{}({})", wrapper, args.join(", "));
+            }
+        }
+
+        raw.to_string()
+    }
+
+    /// Splits `a, Map(b, c), 'd, e'` by commas on the top level.
+    pub fn split_args(raw: &str) -> Vec<&str> {
+        let mut args = Vec::new();
+        let mut depth = 0usize;
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut start = 0;
+
+        for (idx, c) in raw.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '\'' => quoted = !quoted,
+                '(' if !quoted => depth += 1,
+                ')' if !quoted => depth = depth.saturating_sub(1),
+                ',' if !quoted && depth == 0 => {
+                    args.push(raw[start..idx].trim());
+                    start = idx + 1;
+                }
+                _ => {}
+            }
+        }
+
+        args.push(raw[start..].trim());
+        args
+    }
+}
@@ -0,0 +1,123 @@
+use ch2rs_runtime::{
+    __private::{normalize_type, split_args, verify_columns},
+    SchemaMismatch,
+};
+
+fn actual(columns: &[(&str, &str)]) -> Vec<(String, String)> {
+    columns
+        .iter()
+        .map(|(name, type_)| (name.to_string(), type_.to_string()))
+        .collect()
+}
+
+#[test]
+fn normalize() {
+    assert_eq!(normalize_type("String"), "String");
+    assert_eq!(normalize_type(" UInt8 "), "UInt8");
+    assert_eq!(normalize_type("LowCardinality(String)"), "String");
+    assert_eq!(
+        normalize_type("LowCardinality(Nullable(String))"),
+        "Nullable(String)"
+    );
+    assert_eq!(
+        normalize_type("Nullable(LowCardinality(String))"),
+        "Nullable(String)"
+    );
+    assert_eq!(
+        normalize_type("Array(LowCardinality(String))"),
+        "Array(String)"
+    );
+    assert_eq!(
+        normalize_type("Map(LowCardinality(String), Array(Nullable(UInt64)))"),
+        "Map(String, Array(Nullable(UInt64)))"
+    );
+    assert_eq!(
+        normalize_type("Tuple(a LowCardinality(String), b Map(String, UInt8))"),
+        "Tuple(String, Map(String, UInt8))"
+    );
+    assert_eq!(
+        normalize_type("SimpleAggregateFunction(anyLast, LowCardinality(String))"),
+        "String"
+    );
+    assert_eq!(
+        normalize_type("Enum8('a, b' = 1, 'c' = 2)"),
+        "Enum8('a, b' = 1, 'c' = 2)"
+    );
+    assert_eq!(
+        normalize_type("DateTime64(3, 'UTC')"),
+        "DateTime64(3, 'UTC')"
+    );
+}
+
+#[test]
+fn split() {
+    assert_eq!(split_args("UInt8"), ["UInt8"]);
+    assert_eq!(
+        split_args("String, Map(String, UInt8), 'a, b'"),
+        ["String", "Map(String, UInt8)", "'a, b'"]
+    );
+    assert_eq!(
+        split_args(r"'it\'s, (' = 1, 'b' = 2"),
+        [r"'it\'s, (' = 1", "'b' = 2"]
+    );
+}
+
+#[test]
+fn columns() {
+    let expected = [
+        ("id", "UInt64"),
+        ("name", "String"),
+        ("tags", "Array(String)"),
+    ];
+
+    let same = actual(&[
+        ("id", "UInt64"),
+        ("name", "LowCardinality(String)"),
+        ("tags", "Array(LowCardinality(String))"),
+    ]);
+    assert!(verify_columns(&expected, &[], &same).is_empty());
+
+    let changed = actual(&[("name", "UInt64"), ("id", "UInt64"), ("extra", "String")]);
+    assert_eq!(
+        verify_columns(&expected, &[], &changed),
+        [
+            "`name` is `UInt64`, not `String`",
+            "`tags` is missing",
+            "`extra` is extra",
+            "columns are `name, id`, not `id, name`",
+        ]
+    );
+}
+
+#[test]
+fn ignored() {
+    let expected = [("id", "UInt64"), ("secret", "String")];
+    let ignored = ["secret", "extra"];
+
+    // Ignored columns may be present, absent or of any type.
+    for columns in [
+        &[("id", "UInt64")][..],
+        &[("secret", "UInt8"), ("id", "UInt64"), ("extra", "String")][..],
+    ] {
+        let problems = verify_columns(&expected, &ignored, &actual(columns));
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fetch_error() {
+    let client = clickhouse::Client::default().with_url("http://127.0.0.1:1");
+    let err = ch2rs_runtime::__private::verify_schema(&client, "default", "events", &[], &[])
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, SchemaMismatch::Fetch(_)));
+    assert!(err.to_string().starts_with("failed to fetch columns: "));
+    assert!(std::error::Error::source(&err).is_some());
+
+    let err = SchemaMismatch::Columns("default.events".into(), vec!["`a` is extra".into()]);
+    assert_eq!(
+        err.to_string(),
+        "`default.events` doesn't match the row: `a` is extra"
+    );
+}
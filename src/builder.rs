@@ -0,0 +1,282 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    codegen, miner,
+    options::{Options, Override, StringRepr, Type},
+    output,
+};
+
+/// Generates rows from `build.rs`.
+///
+/// ```no_run
+/// // build.rs
+/// ch2rs::Builder::new()
+///     .ddl_file("schema/events.sql")
+///     .table("events")
+///     .deserialize(true)
+///     .type_("DateTime", "u32")
+///     .derive("Clone")
+///     .write_to(std::env::var("OUT_DIR").unwrap())
+///     .unwrap();
+/// ```
+///
+/// Every table is written to `<table>.rs` suitable for `include!`:
+/// ```ignore
+/// mod events {
+///     include!(concat!(env!("OUT_DIR"), "/events.rs"));
+/// }
+/// ```
+///
+/// All methods are synchronous, the caller doesn't need a tokio runtime.
+#[derive(Debug, Default)]
+pub struct Builder {
+    options: Options,
+    tables: Vec<String>,
+    types: Vec<(String, String)>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ClickHouse server's URL, `localhost:8123` by default.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.options.connection.url = Some(url.into());
+        self
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.options.connection.user = Some(user.into());
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.options.connection.password = Some(password.into());
+        self
+    }
+
+    /// Passes the setting to the server, e.g. `flatten_nested=0`.
+    pub fn setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let setting = (name.into(), value.into());
+        self.options.connection.settings.push(setting);
+        self
+    }
+
+    /// Adds the HTTP header to requests.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let header = (name.into(), value.into());
+        self.options.connection.headers.push(header);
+        self
+    }
+
+    /// Limits connecting and fetching every schema, it's also passed as
+    /// `max_execution_time`, so the server cancels slower queries.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.connection.timeout = Some(timeout.as_secs().max(1));
+        self
+    }
+
+    /// Enables compression of responses, it's enabled by default.
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.options.connection.no_compression = !enabled;
+        self
+    }
+
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.options.connection.role = Some(role.into());
+        self
+    }
+
+    /// Verifies the server with CA certificates from the PEM file.
+    pub fn ca_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.connection.ca_cert = Some(path.into());
+        self
+    }
+
+    /// Authenticates with the client certificate and its key from PEM files.
+    pub fn client_cert(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.options.connection.client_cert = Some(cert.into());
+        self.options.connection.client_key = Some(key.into());
+        self
+    }
+
+    /// Sets a database where tables are placed in, `default` by default.
+    pub fn database(mut self, database: impl Into<String>) -> Self {
+        self.options.database = database.into();
+        self
+    }
+
+    /// Reads `CREATE TABLE` statements from the file instead of the server.
+    ///
+    /// The path is relative to the current directory, which is the crate's
+    /// root for build scripts.
+    pub fn ddl_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.ddl = Some(path.into());
+        self.options.schema_file = None;
+        self
+    }
+
+    /// Reads tables from a snapshot written by `ch2rs dump` instead of the server.
+    pub fn schema_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.schema_file = Some(path.into());
+        self.options.ddl = None;
+        self
+    }
+
+    /// Adds a table to generate a row for.
+    pub fn table(mut self, table: impl Into<String>) -> Self {
+        self.tables.push(table.into());
+        self
+    }
+
+    /// Adds tables to generate rows for.
+    pub fn tables<T: Into<String>>(mut self, tables: impl IntoIterator<Item = T>) -> Self {
+        self.tables.extend(tables.into_iter().map(Into::into));
+        self
+    }
+
+    /// Generates `Serialize` instances.
+    pub fn serialize(mut self, enabled: bool) -> Self {
+        self.options.serialize = enabled;
+        self
+    }
+
+    /// Generates `Deserialize` instances.
+    pub fn deserialize(mut self, enabled: bool) -> Self {
+        self.options.deserialize = enabled;
+        self
+    }
+
+    /// Generates only owned types.
+    pub fn owned(mut self, enabled: bool) -> Self {
+        self.options.owned = enabled;
+        self
+    }
+
+    /// Generates `verify_schema()` comparing columns with the server at runtime.
+    pub fn verify_schema(mut self, enabled: bool) -> Self {
+        self.options.verify_schema = enabled;
+        self
+    }
+
+    /// Generates `insert()` and `select()` using the table's name.
+    pub fn query_helpers(mut self, enabled: bool) -> Self {
+        self.options.query_helpers = enabled;
+        self
+    }
+
+    /// Generates `inserter()` along with query helpers, it requires the
+    /// `inserter` feature of `clickhouse`.
+    pub fn inserter(mut self, enabled: bool) -> Self {
+        self.options.inserter = enabled;
+        self
+    }
+
+    /// Sets the representation of `String` columns.
+    pub fn string_repr(mut self, repr: StringRepr) -> Self {
+        self.options.string_repr = repr;
+        self
+    }
+
+    /// Uses a borrowed type for the provided column.
+    pub fn borrow(mut self, column: impl Into<String>) -> Self {
+        self.options.borrow.push(column.into());
+        self
+    }
+
+    /// Uses an owned type for the provided column.
+    pub fn own(mut self, column: impl Into<String>) -> Self {
+        self.options.own.push(column.into());
+        self
+    }
+
+    /// Overrides the type, e.g. `.type_("Decimal(18, 9)", "fixnum::FixedPoint<i64, typenum::U9>")`.
+    ///
+    /// The SQL type is validated by [`Builder::write_to`].
+    pub fn type_(mut self, sql: impl Into<String>, type_: impl Into<String>) -> Self {
+        self.types.push((sql.into(), type_.into()));
+        self
+    }
+
+    /// Overrides the type of the provided column.
+    pub fn override_type(mut self, column: impl Into<String>, type_: impl Into<String>) -> Self {
+        self.options.overrides.push(Override {
+            column: column.into(),
+            type_: type_.into(),
+        });
+        self
+    }
+
+    /// Adds `#[serde(with = "serde_bytes")]` to the provided column.
+    pub fn bytes(mut self, column: impl Into<String>) -> Self {
+        self.options.bytes.push(column.into());
+        self
+    }
+
+    /// Ignores the provided column.
+    pub fn ignore(mut self, column: impl Into<String>) -> Self {
+        self.options.ignore.push(column.into());
+        self
+    }
+
+    /// Adds `#[derive(<trait>)]` to the generated types.
+    pub fn derive(mut self, trait_: impl Into<String>) -> Self {
+        self.options.derives.push(trait_.into());
+        self
+    }
+
+    /// Generates rows and writes them to `<dir>/<table>.rs`.
+    ///
+    /// Fails if called within a tokio runtime, since it blocks the thread.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+
+        if self.tables.is_empty() {
+            bail!("no tables are specified");
+        }
+
+        let mut options = self.options.clone();
+
+        for (sql, type_) in &self.types {
+            options.types.push(Type {
+                sql: miner::parse_type(sql)
+                    .with_context(|| format!("failed to parse the `{}` type", sql))?,
+                type_: type_.clone(),
+            });
+        }
+
+        for path in options.ddl.iter().chain(&options.schema_file) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+
+        // `block_on` panics within a runtime, e.g. if called from `#[tokio::main]`.
+        if tokio::runtime::Handle::try_current().is_ok() {
+            bail!("`write_to` blocks the thread, so it can't be called within a tokio runtime");
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("failed to create a runtime")?;
+
+        for table in &self.tables {
+            options.table = table.clone();
+
+            let table = runtime
+                .block_on(crate::load(&options, None))
+                .with_context(|| format!("failed to load the `{}` table", table))?;
+
+            let code = codegen::generate_for_include(&table, &options)?;
+            let path = dir.join(format!("{}.rs", options.table));
+            output::write(&path, &code)?;
+        }
+
+        Ok(())
+    }
+}
@@ -2,79 +2,438 @@ use std::fmt::Write;
 
 use anyhow::{bail, Context, Result};
 use heck::{ToSnakeCase, ToUpperCamelCase};
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{visit::Visit, visit_mut::VisitMut};
 
 use crate::{
-    options::Options,
+    diff,
+    layout::Layout,
+    miner,
+    options::{Options, SchemaSource, StringRepr},
     schema::{Column, SqlType, Table},
 };
 
-fn generate_prelude(dst: &mut impl Write, options: &Options) -> Result<()> {
+const OPTIONS_PREFIX: &str = "// Generated with the following options:\n/*\n";
+const FINGERPRINT_PREFIX: &str = "// Schema fingerprint: ";
+
+fn generate_prelude(dst: &mut impl Write, tables: &[&Table], options: &Options) -> Result<()> {
     let version = env!("CARGO_PKG_VERSION");
 
     writeln!(dst, "// GENERATED CODE (ch2rs v{})", version)?;
-    writeln!(dst, "#![cfg_attr(rustfmt, rustfmt::skip)]")?;
-    writeln!(dst, "#![allow(warnings)]")?;
-    writeln!(dst, "#![allow(clippy::all)]")?;
-    writeln!(dst, "\n// Generated with the following options:")?;
-    writeln!(dst, "/*\n{}\n*/", options.format().replace('\n', "\n    "))?;
+    writeln!(dst)?;
+    write!(dst, "{}", OPTIONS_PREFIX)?;
+    // Only continuation lines are indented, multiline queries are kept as is.
+    let formatted = options.format().replace("\\\n", "\\\n    ");
+    writeln!(dst, "{}\n*/", formatted)?;
+    writeln!(dst, "\n{}{:016x}", FINGERPRINT_PREFIX, fingerprint(tables))?;
+
+    if let (Some(other), [table, other_table]) = (&options.compatible_with, tables) {
+        let source = if let Some(path) = &options.ddl {
+            SchemaSource::Ddl(path.clone())
+        } else if let Some(path) = &options.schema_file {
+            SchemaSource::Snapshot(path.clone())
+        } else {
+            SchemaSource::Server(None)
+        };
+
+        writeln!(dst, "// Compatible with schema versions:")?;
+        writeln!(dst, "//   {:016x} {}", fingerprint(&[table]), source)?;
+        writeln!(dst, "//   {:016x} {}", fingerprint(&[other_table]), other)?;
+    }
 
     Ok(())
 }
 
-fn generate_row(dst: &mut impl Write, table: &Table, options: &Options) -> Result<()> {
-    writeln!(dst, "#[derive(Debug, clickhouse::Row)]")?;
+// FNV-1a of names and types of columns, it must be stable between versions.
+// Ignored columns are hashed as well, so `ignore` doesn't change the schema.
+fn fingerprint(tables: &[&Table]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+
+    for table in tables {
+        let mut entries = table
+            .columns
+            .iter()
+            .map(|c| (c.position, format!("{} {}\n", c.name, c.type_)))
+            .chain(
+                (table.ignored.iter()).map(|c| (c.position, format!("{} {}\n", c.name, c.type_))),
+            )
+            .collect::<Vec<_>>();
+
+        // The sort is stable, so tables without positions keep their order.
+        entries.sort_by_key(|(position, _)| *position);
+
+        for (_, entry) in entries {
+            for byte in entry.bytes() {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+    }
 
-    if options.serialize {
-        writeln!(dst, "#[derive(serde::Serialize)]")?;
+    hash
+}
+
+/// Extracts formatted options from the prelude of generated code.
+pub fn parse_options(code: &str) -> Option<&str> {
+    let (_, rest) = code.split_once(OPTIONS_PREFIX)?;
+    let (options, _) = rest.split_once("\n*/")?;
+    Some(options)
+}
+
+/// Extracts the schema fingerprint from the prelude of generated code.
+pub fn parse_fingerprint(code: &str) -> Option<&str> {
+    code.lines()
+        .find_map(|line| line.strip_prefix(FINGERPRINT_PREFIX))
+        .map(str::trim)
+}
+
+fn generate_attributes() -> TokenStream {
+    quote! {
+        #![allow(warnings)]
+        #![allow(clippy::all)]
     }
+}
 
-    if options.deserialize {
-        writeln!(dst, "#[derive(serde::Deserialize)]")?;
+// `include!` prohibits inner attributes, so lints are allowed on every item instead.
+fn allow_lints(items: TokenStream) -> Result<TokenStream> {
+    let mut file = syn::parse2::<syn::File>(items).context("generated code is invalid")?;
+    add_allow(&mut file.items);
+    Ok(file.into_token_stream())
+}
+
+fn add_allow(items: &mut [syn::Item]) {
+    let allow: syn::Attribute = syn::parse_quote! { #[allow(warnings, clippy::all)] };
+
+    for item in items {
+        let attrs = match item {
+            // Modules are kept without attributes to be separated by empty lines.
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => {
+                add_allow(items);
+                continue;
+            }
+            syn::Item::Const(item) => &mut item.attrs,
+            syn::Item::Enum(item) => &mut item.attrs,
+            syn::Item::Fn(item) => &mut item.attrs,
+            syn::Item::Impl(item) => &mut item.attrs,
+            syn::Item::Struct(item) => &mut item.attrs,
+            _ => continue,
+        };
+
+        // After docs and derives, so the item reads as usual.
+        attrs.push(allow.clone());
     }
+}
+
+fn generate_row_doc(table: &Table) -> TokenStream {
+    let mut lines = table
+        .comment
+        .lines()
+        .map(Into::into)
+        .collect::<Vec<String>>();
 
-    for derive in &options.derives {
-        writeln!(dst, "#[derive({})]", derive)?;
+    if !lines.is_empty() {
+        lines.push(String::new());
     }
 
-    let mut buffer = String::new();
+    // Rows of queries have no table.
+    if !table.name.is_empty() {
+        lines.push(format!("* Table: `{}.{}`", table.database, table.name));
+    }
 
-    for column in &table.columns {
-        generate_field(&mut buffer, column, options)
-            .with_context(|| format!("failed to generate the `{}` field", column.name))?;
+    let items = [
+        ("Engine", &table.engine),
+        ("Partition key", &table.partition_key),
+        ("Sorting key", &table.sorting_key),
+        ("Primary key", &table.primary_key),
+        ("TTL", &table.ttl),
+    ];
+
+    for (title, value) in items {
+        if !value.is_empty() {
+            lines.push(format!("* {}: `{}`", title, value));
+        }
     }
 
-    let has_lifetime = buffer.contains("'a");
-    if has_lifetime {
-        writeln!(dst, "pub struct Row<'a> {{")?;
-    } else {
-        writeln!(dst, "pub struct Row {{")?;
+    generate_doc(lines.iter().map(String::as_str))
+}
+
+fn generate_doc<'a>(lines: impl Iterator<Item = &'a str>) -> TokenStream {
+    lines
+        .map(|line| {
+            let line = if line.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", line)
+            };
+
+            quote! { #[doc = #line] }
+        })
+        .collect()
+}
+
+fn generate_derives(builtin: &[&str], options: &Options) -> Result<TokenStream> {
+    let paths = builtin
+        .iter()
+        .copied()
+        .chain(options.derives.iter().map(String::as_str))
+        .map(|derive| {
+            syn::parse_str::<syn::Path>(derive)
+                .with_context(|| format!("invalid derive `{}`", derive))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! { #[derive(#(#paths),*)] })
+}
+
+// Columns from `unverified` aren't checked by `verify_schema()`.
+fn generate_row(table: &Table, unverified: &[&str], options: &Options) -> Result<TokenStream> {
+    let doc = generate_row_doc(table);
+    let ident = make_ident(options.struct_name.as_deref().unwrap_or("Row"))?;
+    if ident == "Ch2rsColumn" {
+        bail!("`Ch2rsColumn` describes columns of the row, use another struct name");
     }
 
-    dst.write_str(&buffer)?;
-    writeln!(dst, "}}")?;
-    Ok(())
+    let (row, anonymous) = generate_struct(&ident, doc, &table.columns, options)?;
+
+    let name = &table.name;
+    let database = &table.database;
+    let engine = &table.engine;
+
+    let consts = (!table.name.is_empty()).then(|| {
+        quote! {
+            pub const TABLE: &'static str = #name;
+            pub const DATABASE: &'static str = #database;
+            pub const ENGINE: &'static str = #engine;
+        }
+    });
+
+    let columns = table.columns.iter().map(|column| {
+        let name = &column.name;
+        let type_ = column.type_.to_string();
+        let default_kind = match column.default_kind {
+            Some(kind) => {
+                let kind = kind.as_str();
+                quote! { Some(#kind) }
+            }
+            None => quote! { None },
+        };
+
+        quote! {
+            Ch2rsColumn {
+                name: #name,
+                type_: #type_,
+                default_kind: #default_kind,
+            }
+        }
+    });
+
+    let helpers = (options.query_helpers && !table.name.is_empty())
+        .then(|| generate_query_helpers(table, &ident, &anonymous, options));
+    let verify = (options.verify_schema && !table.name.is_empty())
+        .then(|| generate_verify_schema(unverified, &ident, &anonymous, options));
+
+    Ok(quote! {
+        #row
+
+        impl #ident #anonymous {
+            #consts
+
+            /// Columns of the row in the server order.
+            pub const COLUMNS: &'static [Ch2rsColumn] = &[#(#columns,)*];
+
+            pub fn column_names() -> impl Iterator<Item = &'static str> {
+                Self::COLUMNS.iter().map(|column| column.name)
+            }
+        }
+
+        /// A column of the row, see `COLUMNS`.
+        ///
+        /// The prefix prevents clashes with types generated for columns.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Ch2rsColumn {
+            pub name: &'static str,
+            /// The ClickHouse type without wrappers like `LowCardinality`.
+            pub type_: &'static str,
+            /// `DEFAULT`, `MATERIALIZED`, `ALIAS` or `EPHEMERAL`.
+            pub default_kind: Option<&'static str>,
+        }
+
+        #helpers
+        #verify
+    })
 }
 
-fn generate_field(dst: &mut impl Write, column: &Column, options: &Options) -> Result<()> {
-    if let Some(attr) = make_attribute(column, options) {
-        writeln!(dst, "{}", attr)?;
+fn generate_query_helpers(
+    table: &Table,
+    ident: &Ident,
+    anonymous: &TokenStream,
+    options: &Options,
+) -> TokenStream {
+    // Names may be reserved words or contain any characters, e.g. `order-items`.
+    let name = format!(
+        "{}.{}",
+        miner::quote_identifier(&table.database),
+        miner::quote_identifier(&table.name)
+    );
+    let select = format!("SELECT ?fields FROM {}", name);
+
+    // It's opt-in, since the `inserter` feature of `clickhouse` isn't default.
+    let inserter = options.inserter.then(|| {
+        quote! {
+            /// Creates an inserter, requires the `inserter` feature of `clickhouse`.
+            pub fn inserter(
+                client: &clickhouse::Client,
+            ) -> clickhouse::error::Result<clickhouse::inserter::Inserter<Self>> {
+                client.inserter(Self::QUOTED_NAME)
+            }
+        }
+    });
+
+    // Dictionaries are read-only.
+    let insert = (table.engine != "Dictionary").then(|| {
+        quote! {
+            /// Starts inserting rows into the table.
+            pub fn insert(
+                client: &clickhouse::Client,
+            ) -> clickhouse::error::Result<clickhouse::insert::Insert<Self>> {
+                client.insert(Self::QUOTED_NAME)
+            }
+
+            #inserter
+        }
+    });
+
+    quote! {
+        impl #ident #anonymous {
+            /// Selects all rows, extend it to filter them, e.g. `WHERE` or `LIMIT`.
+            pub const SELECT: &'static str = #select;
+
+            const QUOTED_NAME: &'static str = #name;
+
+            #insert
+
+            /// Starts a query selecting all rows of the table.
+            pub fn select(client: &clickhouse::Client) -> clickhouse::query::Query {
+                client.query(Self::SELECT)
+            }
+        }
     }
+}
+
+// Compares `COLUMNS` with `system.columns` at runtime using `ch2rs-runtime`,
+// unverified columns aren't expected, but allowed.
+fn generate_verify_schema(
+    unverified: &[&str],
+    ident: &Ident,
+    anonymous: &TokenStream,
+    options: &Options,
+) -> TokenStream {
+    let ignored = options
+        .ignore
+        .iter()
+        .map(String::as_str)
+        .chain(unverified.iter().copied());
+
+    quote! {
+        impl #ident #anonymous {
+            /// Compares columns of the table on the server with the ones the row
+            /// is generated for, call it at startup to fail fast.
+            pub async fn verify_schema(
+                client: &clickhouse::Client,
+            ) -> Result<(), ch2rs_runtime::SchemaMismatch> {
+                use ch2rs_runtime::__private::verify_schema;
+
+                const IGNORED: &[&str] = &[#(#ignored,)*];
+
+                let expected = Self::COLUMNS.iter().map(|c| (c.name, c.type_)).collect::<Vec<_>>();
+                verify_schema(client, Self::DATABASE, Self::TABLE, &expected, IGNORED).await
+            }
+        }
+    }
+}
+
+// Returns the struct and its anonymous generics to write `impl`s.
+fn generate_struct(
+    name: &Ident,
+    doc: TokenStream,
+    columns: &[Column],
+    options: &Options,
+) -> Result<(TokenStream, TokenStream)> {
+    let mut builtin = vec!["Debug", "clickhouse::Row"];
+
+    if options.serialize {
+        builtin.push("serde::Serialize");
+    }
+
+    if options.deserialize {
+        builtin.push("serde::Deserialize");
+    }
+
+    let derives = generate_derives(&builtin, options)?;
+
+    let mut fields = Vec::new();
+    let mut has_lifetime = false;
+
+    for column in columns {
+        let (field, type_) = generate_field(column, options)
+            .with_context(|| format!("failed to generate the `{}` field", column.name))?;
+        fields.push(field);
+        has_lifetime |= type_.borrowed;
+    }
+
+    let (generics, anonymous) = if has_lifetime {
+        (quote! { <'a> }, quote! { <'_> })
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    let code = quote! {
+        #doc
+        #derives
+        pub struct #name #generics {
+            #(#fields)*
+        }
+    };
 
-    let name = column.name.to_snake_case();
+    Ok((code, anonymous))
+}
+
+fn generate_field(column: &Column, options: &Options) -> Result<(TokenStream, RustType)> {
     let type_ = make_type(column, options)?;
+    let attr = make_attribute(column, options);
+    let borrow = (options.deserialize && type_.needs_borrow()).then(|| quote! { #[serde(borrow)] });
+    let doc = generate_doc(column.comment.lines());
+    let name = make_ident(&column.name.to_snake_case())?;
+    let code = &type_.code;
+
+    let field = quote! {
+        #doc
+        #attr
+        #borrow
+        pub #name: #code,
+    };
 
-    for comment_line in column.comment.lines() {
-        writeln!(dst, "    /// {}", comment_line)?;
+    Ok((field, type_))
+}
+
+fn make_ident(name: &str) -> Result<Ident> {
+    if let Ok(ident) = syn::parse_str::<Ident>(name) {
+        return Ok(ident);
     }
 
-    writeln!(dst, "    pub {}: {},", name, type_)?;
-    Ok(())
+    // Keywords, e.g. `type`, are used as raw identifiers.
+    syn::parse_str::<Ident>(&format!("r#{}", name))
+        .with_context(|| format!("`{}` is not a valid identifier", name))
 }
 
-fn make_attribute(column: &Column, options: &Options) -> Option<String> {
+fn make_attribute(column: &Column, options: &Options) -> Option<TokenStream> {
     if options.bytes.iter().any(|b| b == &column.name) {
         // Works also for `Option<_>`.
-        return Some(r#"    #[serde(with = "serde_bytes")]"#.into());
+        return Some(quote! { #[serde(with = "serde_bytes")] });
     }
 
     // Add nothing if the column is overrided by name or type.
@@ -82,68 +441,139 @@ fn make_attribute(column: &Column, options: &Options) -> Option<String> {
         return None;
     }
 
-    let attr = match column.type_ {
-        SqlType::UUID => r#"    #[serde(with = "::clickhouse::serde::uuid")]"#,
-        SqlType::IPv4 => r#"    #[serde(with = "::clickhouse::serde::ipv4")]"#,
+    let path = match column.type_ {
+        SqlType::UUID => "::clickhouse::serde::uuid",
+        SqlType::IPv4 => "::clickhouse::serde::ipv4",
         SqlType::Nullable(ref inner) => match inner.as_ref() {
-            SqlType::UUID => r#"    #[serde(with = "::clickhouse::serde::uuid::option")]"#,
-            SqlType::IPv4 => r#"    #[serde(with = "::clickhouse::serde::ipv4::option")]"#,
+            SqlType::UUID => "::clickhouse::serde::uuid::option",
+            SqlType::IPv4 => "::clickhouse::serde::ipv4::option",
             _ => return None,
         },
         _ => return None,
     };
 
-    Some(attr.into())
+    Some(quote! { #[serde(with = #path)] })
 }
 
-fn make_type(column: &Column, options: &Options) -> Result<String> {
+struct RustType {
+    code: TokenStream,
+    /// Whether the type borrows from the input, i.e. requires `'a`.
+    borrowed: bool,
+    /// Whether the type contains `Cow`, which is never borrowed implicitly.
+    cow: bool,
+}
+
+impl RustType {
+    // serde borrows only bare `&str` and `&[u8]` implicitly,
+    // other types, e.g. `Vec<&str>` and `Cow<str>`, require `#[serde(borrow)]`.
+    fn needs_borrow(&self) -> bool {
+        self.cow
+            || self.borrowed
+                && !matches!(
+                    syn::parse2::<syn::Type>(self.code.clone()),
+                    Ok(syn::Type::Reference(_))
+                )
+    }
+
+    fn owned(code: TokenStream) -> Self {
+        Self {
+            code,
+            borrowed: false,
+            cow: false,
+        }
+    }
+
+    fn wrap(self, wrap: impl FnOnce(TokenStream) -> TokenStream) -> Self {
+        Self {
+            code: wrap(self.code),
+            ..self
+        }
+    }
+}
+
+fn make_type(column: &Column, options: &Options) -> Result<RustType> {
     do_make_type(&column.name, &column.type_, options)
 }
 
-fn do_make_type(name: &str, sql_type: &SqlType, options: &Options) -> Result<String> {
+fn do_make_type(name: &str, sql_type: &SqlType, options: &Options) -> Result<RustType> {
     if let Some(type_) = find_override(name, sql_type, options) {
-        return Ok(type_.into());
+        let type_ = syn::parse_str::<syn::Type>(type_)
+            .with_context(|| format!("invalid type `{}`", type_))?;
+
+        return Ok(RustType {
+            borrowed: has_lifetime(&type_),
+            code: type_.into_token_stream(),
+            cow: false,
+        });
     }
 
     Ok(match sql_type {
-        SqlType::UInt8 => "u8".into(),
-        SqlType::UInt16 => "u16".into(),
-        SqlType::UInt32 => "u32".into(),
-        SqlType::UInt64 => "u64".into(),
-        SqlType::UInt128 => "u128".into(),
-        SqlType::Int8 => "i8".into(),
-        SqlType::Int16 => "i16".into(),
-        SqlType::Int32 => "i32".into(),
-        SqlType::Int64 => "i64".into(),
-        SqlType::Int128 => "i128".into(),
-        SqlType::Bool => "bool".into(),
-        SqlType::String if options.owned => "String".into(),
-        SqlType::String => "&'a str".into(),
+        SqlType::UInt8 => RustType::owned(quote! { u8 }),
+        SqlType::UInt16 => RustType::owned(quote! { u16 }),
+        SqlType::UInt32 => RustType::owned(quote! { u32 }),
+        SqlType::UInt64 => RustType::owned(quote! { u64 }),
+        SqlType::UInt128 => RustType::owned(quote! { u128 }),
+        SqlType::Int8 => RustType::owned(quote! { i8 }),
+        SqlType::Int16 => RustType::owned(quote! { i16 }),
+        SqlType::Int32 => RustType::owned(quote! { i32 }),
+        SqlType::Int64 => RustType::owned(quote! { i64 }),
+        SqlType::Int128 => RustType::owned(quote! { i128 }),
+        SqlType::Bool => RustType::owned(quote! { bool }),
+        SqlType::String => match options.string_repr(name) {
+            StringRepr::Str => RustType {
+                code: quote! { &'a str },
+                borrowed: true,
+                cow: false,
+            },
+            StringRepr::String => RustType::owned(quote! { String }),
+            StringRepr::Cow => RustType {
+                code: quote! { ::std::borrow::Cow<'a, str> },
+                borrowed: true,
+                cow: true,
+            },
+        },
         // SqlType::FixedString(size) => todo!(),
-        SqlType::Float32 => "f32".into(),
-        SqlType::Float64 => "f64".into(),
+        SqlType::Float32 => RustType::owned(quote! { f32 }),
+        SqlType::Float64 => RustType::owned(quote! { f64 }),
         // SqlType::Date => todo!(),
         // SqlType::DateTime(_) => todo!(),
         // SqlType::DateTime64(_, _) => todo!(),
-        SqlType::IPv4 => "::std::net::Ipv4Addr".into(),
-        SqlType::IPv6 => "::std::net::Ipv6Addr".into(),
-        SqlType::UUID => "::uuid::Uuid".into(),
+        SqlType::IPv4 => RustType::owned(quote! { ::std::net::Ipv4Addr }),
+        SqlType::IPv6 => RustType::owned(quote! { ::std::net::Ipv6Addr }),
+        SqlType::UUID => RustType::owned(quote! { ::uuid::Uuid }),
         // SqlType::Decimal(_prec, _scale) => todo!(),
-        SqlType::Enum8(_) | SqlType::Enum16(_) => name.to_upper_camel_case(),
-        SqlType::Array(inner) => format!("Vec<{}>", do_make_type(name, inner, options)?),
+        SqlType::Enum8(_) | SqlType::Enum16(_) => {
+            let ident = make_ident(&name.to_upper_camel_case())?;
+            RustType::owned(quote! { #ident })
+        }
+        SqlType::Array(inner) => {
+            do_make_type(name, inner, options)?.wrap(|code| quote! { Vec<#code> })
+        }
         SqlType::Tuple(inner) => {
-            let inner = inner
-                .iter()
-                .map(|i| do_make_type(name, i, options).map(|t| format!("{}, ", t)))
-                .collect::<Result<String>>()?;
-
-            format!("({})", inner)
+            let mut codes = Vec::new();
+            let mut borrowed = false;
+            let mut cow = false;
+
+            for i in inner {
+                let type_ = do_make_type(name, i, options)?;
+                codes.push(type_.code);
+                borrowed |= type_.borrowed;
+                cow |= type_.cow;
+            }
+
+            RustType {
+                code: quote! { (#(#codes,)*) },
+                borrowed,
+                cow,
+            }
         }
         SqlType::Map(key, value) => {
             let tup = Box::new(SqlType::Tuple(vec![(**key).clone(), (**value).clone()]));
             do_make_type(name, &SqlType::Array(tup), options)?
         }
-        SqlType::Nullable(inner) => format!("Option<{}>", do_make_type(name, inner, options)?),
+        SqlType::Nullable(inner) => {
+            do_make_type(name, inner, options)?.wrap(|code| quote! { Option<#code> })
+        }
         _ => bail!(
             "there is no default impl for {}, use -T or -O to specify it",
             sql_type
@@ -151,6 +581,21 @@ fn do_make_type(name: &str, sql_type: &SqlType, options: &Options) -> Result<Str
     })
 }
 
+// Checks whether the user-provided type mentions the `'a` lifetime.
+fn has_lifetime(type_: &syn::Type) -> bool {
+    struct Finder(bool);
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_lifetime(&mut self, lifetime: &'ast syn::Lifetime) {
+            self.0 |= lifetime.ident == "a";
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_type(type_);
+    finder.0
+}
+
 fn find_override<'a>(name: &str, sql_type: &SqlType, options: &'a Options) -> Option<&'a str> {
     // Find override by a column's name.
     if let Some(o) = options.overrides.iter().find(|o| o.column == name) {
@@ -165,7 +610,7 @@ fn find_override<'a>(name: &str, sql_type: &SqlType, options: &'a Options) -> Op
     None
 }
 
-fn generate_enums(dst: &mut impl Write, table: &Table, options: &Options) -> Result<()> {
+fn generate_enums(table: &Table, options: &Options) -> Result<TokenStream> {
     fn find_enum(t: &SqlType) -> Option<(bool, &[(String, i32)])> {
         match t {
             SqlType::Enum8(v) => Some((false, v)),
@@ -177,74 +622,366 @@ fn generate_enums(dst: &mut impl Write, table: &Table, options: &Options) -> Res
         }
     }
 
+    let mut enums = TokenStream::new();
+
     for column in &table.columns {
         if let Some((is_extended, variants)) = find_enum(&column.type_) {
-            generate_enum(
-                dst,
+            enums.extend(generate_enum(
                 &column.name.to_upper_camel_case(),
                 is_extended,
                 variants,
                 options,
-            )?;
-            writeln!(dst)?;
+            )?);
         }
     }
 
-    Ok(())
+    Ok(enums)
 }
 
 fn generate_enum(
-    dst: &mut impl Write,
     name: &str,
     is_extended: bool,
     variants: &[(String, i32)],
     options: &Options,
-) -> Result<()> {
-    writeln!(dst, "#[derive(Debug)]")?;
+) -> Result<TokenStream> {
+    let mut builtin = vec!["Debug"];
 
     if options.serialize {
-        writeln!(dst, "#[derive(serde_repr::Serialize_repr)]")?;
+        builtin.push("serde_repr::Serialize_repr");
     }
 
     if options.deserialize {
-        writeln!(dst, "#[derive(serde_repr::Deserialize_repr)]")?;
+        builtin.push("serde_repr::Deserialize_repr");
     }
 
-    for derive in &options.derives {
-        writeln!(dst, "#[derive({})]", derive)?;
-    }
+    let derives = generate_derives(&builtin, options)?;
 
-    if is_extended {
-        writeln!(dst, "#[repr(i16)]")?;
+    let repr = if is_extended {
+        format_ident!("i16")
     } else {
-        writeln!(dst, "#[repr(i8)]")?;
+        format_ident!("i8")
+    };
+
+    let name = make_ident(name)?;
+    let variants = variants
+        .iter()
+        .map(|(name, value)| {
+            let name = make_ident(&prepare_name_ident(name))?;
+            let sign = (*value < 0).then(|| quote! { - });
+            let value = Literal::u32_unsuffixed(value.unsigned_abs());
+            Ok(quote! { #name = #sign #value, })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #derives
+        #[repr(#repr)]
+        pub enum #name {
+            #(#variants)*
+        }
+    })
+}
+
+fn prepare_name_ident(name: &str) -> String {
+    if name.trim().is_empty() {
+        "Empty".into()
+    } else {
+        name.to_upper_camel_case()
+    }
+}
+
+fn unparse(code: TokenStream) -> Result<String> {
+    let mut file = syn::parse2::<syn::File>(code).context("generated code is invalid")?;
+    let mut layout = Layout::default();
+    layout.visit_file_mut(&mut file);
+
+    // `prettyplease` doesn't separate items by empty lines, so do it manually.
+    let mut code = String::new();
+
+    for attr in file.attrs {
+        code.push_str(unparse_one(vec![attr], Vec::new()).trim_end());
+        code.push('\n');
+    }
+
+    for item in file.items {
+        code.push('\n');
+        unparse_item(&mut code, item, "");
     }
 
-    writeln!(dst, "pub enum {} {{", name)?;
+    Ok(layout.restore(&code))
+}
+
+pub(crate) fn unparse_one(attrs: Vec<syn::Attribute>, items: Vec<syn::Item>) -> String {
+    prettyplease::unparse(&syn::File {
+        shebang: None,
+        attrs,
+        items,
+    })
+}
 
-    for (name, value) in variants {
-        writeln!(dst, "    {} = {},", prepare_name_ident(name), value)?;
+fn unparse_item(code: &mut String, item: syn::Item, indent: &str) {
+    match item {
+        syn::Item::Mod(syn::ItemMod {
+            attrs,
+            vis,
+            ident,
+            content: Some((_, items)),
+            ..
+        }) if attrs.is_empty() => {
+            let _ = writeln!(code, "{}{} mod {} {{", indent, vis.to_token_stream(), ident);
+
+            for (idx, item) in items.into_iter().enumerate() {
+                if idx > 0 {
+                    code.push('\n');
+                }
+
+                unparse_item(code, item, &format!("{}    ", indent));
+            }
+
+            let _ = writeln!(code, "{}}}", indent);
+        }
+        item => {
+            for line in unparse_one(Vec::new(), vec![item]).lines() {
+                if !line.is_empty() {
+                    code.push_str(indent);
+                    code.push_str(line);
+                }
+                code.push('\n');
+            }
+        }
     }
+}
+
+/// Generates items without the prelude, e.g. to be expanded by a macro.
+pub fn generate_items(table: &Table, options: &Options) -> Result<TokenStream> {
+    do_generate_items(table, &[], options)
+}
+
+fn do_generate_items(table: &Table, unverified: &[&str], options: &Options) -> Result<TokenStream> {
+    let row = generate_row(table, unverified, options).context("failed to generate a row")?;
+    let enums = generate_enums(table, options).context("failed to generate enums")?;
+
+    Ok(quote! {
+        #row
+        #enums
+    })
+}
+
+/// A table or a described query, generated into a separate module if `module` is set.
+pub struct Unit {
+    pub module: Option<String>,
+    pub query: Option<String>,
+    pub table: Table,
+}
+
+fn generate_unit(unit: &Unit, options: &Options) -> Result<TokenStream> {
+    let mut items = generate_items(&unit.table, options)?;
+
+    if let Some(query) = &unit.query {
+        let params = miner::params(query).context("failed to parse parameters")?;
+        let params = generate_params(&params, options).context("failed to generate params")?;
+
+        // `?` is a placeholder for `Query::bind()`.
+        let text = query.trim().replace('?', "??");
+
+        items = quote! {
+            pub const QUERY: &str = #text;
+
+            #items
+            #params
+        };
+    }
+
+    Ok(match &unit.module {
+        Some(module) => {
+            let module = make_ident(&module.to_snake_case())?;
+            quote! {
+                pub mod #module {
+                    #items
+                }
+            }
+        }
+        None => items,
+    })
+}
 
-    writeln!(dst, "}}")?;
+/// Checks that every parameter has a Rust type, e.g. `DateTime` requires `-T`.
+pub(crate) fn check_params(params: &[Column], options: &Options) -> Result<()> {
+    for param in params {
+        make_param_type(param, options)?;
+    }
 
     Ok(())
 }
 
-fn prepare_name_ident(name: &str) -> String {
-    if name.trim().is_empty() {
-        "Empty".into()
-    } else {
-        name.to_upper_camel_case()
+fn make_param_type(param: &Column, options: &Options) -> Result<RustType> {
+    make_type(param, options).with_context(|| {
+        format!(
+            "failed to generate the `{}` param of type `{}`",
+            param.name, param.type_
+        )
+    })
+}
+
+fn generate_params(params: &[Column], options: &Options) -> Result<TokenStream> {
+    if params.is_empty() {
+        return Ok(TokenStream::new());
     }
+
+    let derives = generate_derives(&["Debug"], options)?;
+
+    let mut fields = Vec::new();
+    let mut binds = Vec::new();
+    let mut has_lifetime = false;
+
+    for param in params {
+        let type_ = make_param_type(param, options)?;
+        let name = make_ident(&param.name.to_snake_case())?;
+        let code = &type_.code;
+        let raw_name = &param.name;
+
+        binds.push(quote! { q = q.param(#raw_name, self.#name); });
+        let field = quote! { pub #name: #code, };
+
+        fields.push(field);
+        has_lifetime |= type_.borrowed;
+    }
+
+    let (generics, anonymous) = if has_lifetime {
+        (quote! { <'a> }, quote! { <'_> })
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    Ok(quote! {
+        /// Parameters of the query.
+        #derives
+        pub struct Params #generics {
+            #(#fields)*
+        }
+
+        impl Params #anonymous {
+            /// Sets parameters of the query, their names and types are checked by the compiler.
+            pub fn bind_params(self, mut q: clickhouse::query::Query) -> clickhouse::query::Query {
+                #(#binds)*
+                q
+            }
+        }
+    })
 }
 
 pub fn generate(table: &Table, options: &Options) -> Result<String> {
+    let items = generate_items(table, options)?;
+    do_generate(items, &[table], options, true)
+}
+
+/// Generates code without inner attributes, which are prohibited by `include!`.
+pub fn generate_for_include(table: &Table, options: &Options) -> Result<String> {
+    let items = generate_items(table, options)?;
+    do_generate(items, &[table], options, false)
+}
+
+/// Generates the dictionary's row and the `Key` struct with its key columns.
+pub fn generate_dictionary(key: &Table, row: &Table, options: &Options) -> Result<String> {
+    let doc = format!("Key of the `{}.{}` dictionary.", key.database, key.name);
+    let doc = generate_doc([doc.as_str()].into_iter());
+    let (key, _) = generate_struct(&format_ident!("Key"), doc, &key.columns, options)
+        .context("failed to generate a key")?;
+    let items = generate_items(row, options)?;
+
+    do_generate(
+        quote! {
+            #items
+            #key
+        },
+        &[row],
+        options,
+        true,
+    )
+}
+
+/// Generates a row readable in both versions of the table.
+///
+/// Both versions must have the same columns, `?fields` can't select a column
+/// from the version without it, so such columns must be ignored.
+pub fn generate_compatible(table: &Table, other: &Table, options: &Options) -> Result<String> {
+    let find = |table: &Table, name: &str| table.columns.iter().find(|c| c.name == name).cloned();
+
+    let only_table = table
+        .columns
+        .iter()
+        .filter(|c| find(other, &c.name).is_none());
+    let only_other = other
+        .columns
+        .iter()
+        .filter(|c| find(table, &c.name).is_none());
+
+    if let Some(column) = only_table.chain(only_other).next() {
+        bail!(
+            "the `{}` column exists only in one version, so it can't be read from both, use -I for it",
+            column.name
+        );
+    }
+
+    for column in &table.columns {
+        let other_column = find(other, &column.name).expect("checked above");
+        let forward = diff::compare_types(&other_column.type_, &column.type_);
+        let backward = diff::compare_types(&column.type_, &other_column.type_);
+
+        if forward.is_breaking() || backward.is_breaking() {
+            bail!(
+                "the `{}` column is `{}` in one version and `{}` in another, use -O or -I for it",
+                column.name,
+                column.type_,
+                other_column.type_,
+            );
+        }
+    }
+
+    // Types of common columns may differ in details, e.g. timezones.
+    let unverified = table
+        .columns
+        .iter()
+        .filter(|column| find(other, &column.name).is_some_and(|o| o.type_ != column.type_))
+        .map(|column| column.name.as_str())
+        .collect::<Vec<_>>();
+
+    let items = do_generate_items(table, &unverified, options)?;
+    do_generate(items, &[table, other], options, true)
+}
+
+/// Generates rows of tables and queries, and params of queries.
+pub fn generate_units(units: &[Unit], options: &Options) -> Result<String> {
+    let items = units
+        .iter()
+        .map(|unit| generate_unit(unit, options))
+        .collect::<Result<TokenStream>>()?;
+    let tables = units.iter().map(|unit| &unit.table).collect::<Vec<_>>();
+
+    do_generate(items, &tables, options, true)
+}
+
+fn do_generate(
+    items: TokenStream,
+    tables: &[&Table],
+    options: &Options,
+    with_attributes: bool,
+) -> Result<String> {
     let mut code = String::new();
-    generate_prelude(&mut code, options).context("failed to generate a prelude")?;
-    writeln!(code)?;
-    generate_row(&mut code, table, options).context("failed to generate a row")?;
+    generate_prelude(&mut code, tables, options).context("failed to generate a prelude")?;
     writeln!(code)?;
-    generate_enums(&mut code, table, options).context("failed to generate enums")?;
+
+    let items = if with_attributes {
+        let attributes = generate_attributes();
+        quote! {
+            #attributes
+            #items
+        }
+    } else {
+        allow_lints(items)?
+    };
+
+    code.push_str(&unparse(items)?);
+
     Ok(code.trim().to_string())
 }
@@ -0,0 +1,219 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::options::{Connection, Options, Override, Type};
+
+/// The project's configuration, usually `ch2rs.toml`.
+///
+/// Global settings are applied to every table, sections of tables add their
+/// own settings and the output path:
+/// ```toml
+/// database = "analytics"
+/// deserialize = true
+/// owned = true
+/// derives = ["Clone"]
+///
+/// [connection]
+/// url = "localhost:8123"
+///
+/// [types]
+/// DateTime = "u32"
+///
+/// [tables.events]
+/// struct = "Event"
+/// output = "src/rows/events.rs"
+/// overrides = { payload = "Vec<u8>" }
+/// bytes = ["payload"]
+/// ignore = ["secret"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+#[non_exhaustive]
+pub struct Config {
+    pub connection: Connection,
+    /// A directory to write tables without `output` to, as `<table>.rs`.
+    pub out_dir: Option<PathBuf>,
+    pub database: Option<String>,
+    pub ddl: Option<PathBuf>,
+    pub schema_file: Option<PathBuf>,
+    pub serialize: bool,
+    pub deserialize: bool,
+    pub owned: bool,
+    pub string_repr: Option<String>,
+    /// Generate `verify_schema()` for every table.
+    pub verify_schema: bool,
+    /// Generate `insert()` and `select()` for every table.
+    pub query_helpers: bool,
+    /// Generate `inserter()` as well, it requires the `inserter` feature of `clickhouse`.
+    pub inserter: bool,
+    pub derives: Vec<String>,
+    /// Overrides by SQL types, e.g. `DateTime = "u32"`.
+    pub types: BTreeMap<String, String>,
+    pub tables: BTreeMap<String, TableConfig>,
+}
+
+/// A section of the table, its name may contain a database, e.g. `[tables."db.events"]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+#[non_exhaustive]
+pub struct TableConfig {
+    /// A file to write the generated code to.
+    pub output: Option<PathBuf>,
+    /// The name of the generated struct instead of `Row`.
+    #[serde(rename = "struct")]
+    pub struct_name: Option<String>,
+    pub database: Option<String>,
+    pub ddl: Option<PathBuf>,
+    pub schema_file: Option<PathBuf>,
+    pub derives: Vec<String>,
+    /// Overrides by names of columns, e.g. `payload = "Vec<u8>"`.
+    pub overrides: BTreeMap<String, String>,
+    pub bytes: Vec<String>,
+    pub ignore: Vec<String>,
+    pub borrow: Vec<String>,
+    pub own: Vec<String>,
+}
+
+/// A file to generate and options to generate it with.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub output: PathBuf,
+    pub options: Options,
+}
+
+impl Config {
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        toml::from_str(toml).context("invalid config")
+    }
+
+    /// Reads the config, relative paths in it are resolved against its directory.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut config = Self::from_toml(&toml)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let resolve = |path: &mut Option<PathBuf>| {
+            if let Some(path) = path {
+                *path = dir.join(&*path);
+            }
+        };
+
+        resolve(&mut config.out_dir);
+        resolve(&mut config.ddl);
+        resolve(&mut config.schema_file);
+        resolve(&mut config.connection.ca_cert);
+        resolve(&mut config.connection.client_cert);
+        resolve(&mut config.connection.client_key);
+
+        for table in config.tables.values_mut() {
+            resolve(&mut table.output);
+            resolve(&mut table.ddl);
+            resolve(&mut table.schema_file);
+        }
+
+        Ok(config)
+    }
+
+    /// Returns options of every table in the order of their names.
+    pub fn targets(&self) -> Result<Vec<Target>> {
+        self.tables
+            .iter()
+            .map(|(name, table)| {
+                self.make_target(name, table)
+                    .with_context(|| format!("invalid settings of the `{}` table", name))
+            })
+            .collect()
+    }
+
+    fn make_target(&self, name: &str, table: &TableConfig) -> Result<Target> {
+        let (database, name) = match name.split_once('.') {
+            Some((database, name)) => (Some(database), name),
+            None => (None, name),
+        };
+
+        let output = match (&table.output, &self.out_dir) {
+            (Some(output), _) => output.clone(),
+            (None, Some(dir)) => dir.join(format!("{}.rs", name)),
+            (None, None) => bail!("`output` or global `out-dir` must be specified"),
+        };
+
+        let database = database
+            .or(table.database.as_deref())
+            .or(self.database.as_deref())
+            .unwrap_or("default");
+
+        let mut ddl = table.ddl.clone();
+        let mut schema_file = table.schema_file.clone();
+
+        // The table's source replaces the global one.
+        if ddl.is_none() && schema_file.is_none() {
+            ddl = self.ddl.clone();
+            schema_file = self.schema_file.clone();
+        }
+
+        if ddl.is_some() && schema_file.is_some() {
+            bail!("`ddl` and `schema-file` are mutually exclusive");
+        }
+
+        let types = self
+            .types
+            .iter()
+            .map(|(sql, type_)| {
+                Ok(Type {
+                    sql: crate::parse_type(sql)
+                        .with_context(|| format!("invalid type `{}`", sql))?,
+                    type_: type_.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let overrides = table
+            .overrides
+            .iter()
+            .map(|(column, type_)| Override {
+                column: column.clone(),
+                type_: type_.clone(),
+            })
+            .collect();
+
+        let string_repr = match &self.string_repr {
+            Some(repr) => repr.parse()?,
+            None => Options::default().string_repr,
+        };
+
+        let options = Options {
+            connection: self.connection.clone(),
+            database: database.into(),
+            table: name.into(),
+            ddl,
+            schema_file,
+            struct_name: table.struct_name.clone(),
+            serialize: self.serialize,
+            deserialize: self.deserialize,
+            owned: self.owned,
+            string_repr,
+            verify_schema: self.verify_schema,
+            query_helpers: self.query_helpers,
+            inserter: self.inserter,
+            borrow: table.borrow.clone(),
+            own: table.own.clone(),
+            types,
+            overrides,
+            bytes: table.bytes.clone(),
+            ignore: table.ignore.clone(),
+            derives: self.derives.iter().chain(&table.derives).cloned().collect(),
+            ..Options::default()
+        };
+
+        Ok(Target { output, options })
+    }
+}
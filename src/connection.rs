@@ -0,0 +1,226 @@
+use std::{env, fs, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use clickhouse::Client;
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client as HyperClient},
+    rt::TokioExecutor,
+};
+
+use crate::options::Connection;
+
+const DEFAULT_URL: &str = "localhost:8123";
+
+// The same as in the `clickhouse` crate.
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl Connection {
+    /// Fills missing settings from `CLICKHOUSE_URL`, `CLICKHOUSE_USER` and
+    /// `CLICKHOUSE_PASSWORD` environment variables, then from
+    /// `~/.clickhouse-client/config.xml`. The password is taken from the config
+    /// only along with the user, the config isn't read if nothing is missing.
+    ///
+    /// With [`Connection::ask_password`] the password is read from the terminal
+    /// instead. The result is resolved, so resolving it again is a no-op.
+    pub fn resolve(&self) -> Result<Connection> {
+        let url = self.url.clone().or_else(|| var("CLICKHOUSE_URL"));
+        let user = self.user.clone().or_else(|| var("CLICKHOUSE_USER"));
+
+        let client_config = if url.is_none() || user.is_none() {
+            read_client_config()?
+        } else {
+            ClientConfig::default()
+        };
+
+        let url = url
+            .or(client_config.url)
+            .unwrap_or_else(|| DEFAULT_URL.into());
+
+        // The config's password belongs to the config's user.
+        let config_password = client_config.password.filter(|_| user.is_none());
+        let user = user.or(client_config.user);
+
+        let password = if self.ask_password {
+            let prompt = format!("Password for {}: ", user.as_deref().unwrap_or("default"));
+            Some(rpassword::prompt_password(prompt).context("failed to read the password")?)
+        } else {
+            self.password
+                .clone()
+                .or_else(|| var("CLICKHOUSE_PASSWORD"))
+                .or(config_password)
+        };
+
+        Ok(Connection {
+            url: Some(url),
+            user,
+            password,
+            ask_password: false,
+            ..self.clone()
+        })
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+#[derive(Default)]
+struct ClientConfig {
+    url: Option<String>,
+    user: Option<String>,
+    password: Option<String>,
+}
+
+// Reads settings of `clickhouse-client`. It's configured for the native
+// protocol, so only the host is taken and the default HTTP port is used.
+fn read_client_config() -> Result<ClientConfig> {
+    let Some(path) =
+        var("HOME").map(|home| PathBuf::from(home).join(".clickhouse-client/config.xml"))
+    else {
+        return Ok(ClientConfig::default());
+    };
+
+    if !path.exists() {
+        return Ok(ClientConfig::default());
+    }
+
+    let xml =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let doc = roxmltree::Document::parse(&xml)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    let get = |name: &str| {
+        doc.root_element()
+            .children()
+            .find(|node| node.has_tag_name(name))
+            .and_then(|node| node.text())
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let secure = get("secure").is_some_and(|s| s == "1" || s == "true");
+    let url = get("host").map(|host| match secure {
+        true => format!("https://{}:8443", host),
+        false => format!("http://{}:8123", host),
+    });
+
+    Ok(ClientConfig {
+        url,
+        user: get("user"),
+        password: get("password"),
+    })
+}
+
+// Returns a client without settings, the default HTTP client is replaced
+// only if a timeout or TLS options are provided.
+pub(crate) fn make_base_client(connection: &Connection) -> Result<Client> {
+    let has_tls = connection.ca_cert.is_some() || connection.client_cert.is_some();
+
+    if connection.timeout.is_none() && !has_tls {
+        return Ok(Client::default());
+    }
+
+    let mut http = HttpConnector::new();
+    http.set_keepalive(Some(TCP_KEEPALIVE));
+    http.set_connect_timeout(connection.timeout.map(Duration::from_secs));
+    http.enforce_http(false);
+
+    let builder = {
+        let mut builder = HyperClient::builder(TokioExecutor::new());
+        builder.pool_idle_timeout(POOL_IDLE_TIMEOUT);
+        builder
+    };
+
+    #[cfg(feature = "rustls-tls")]
+    {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(make_rustls_config(connection)?)
+            .https_or_http()
+            .enable_http1()
+            .wrap_connector(http);
+        Ok(Client::with_http_client(builder.build(connector)))
+    }
+
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    {
+        let tls = make_native_tls_connector(connection)?;
+        let connector = hyper_tls::HttpsConnector::from((http, tls.into()));
+        Ok(Client::with_http_client(builder.build(connector)))
+    }
+
+    #[cfg(not(any(feature = "rustls-tls", feature = "native-tls")))]
+    {
+        if has_tls {
+            anyhow::bail!("TLS options require the `rustls-tls` or `native-tls` feature");
+        }
+
+        Ok(Client::with_http_client(builder.build(http)))
+    }
+}
+
+#[cfg(feature = "rustls-tls")]
+fn make_rustls_config(connection: &Connection) -> Result<rustls::ClientConfig> {
+    use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+
+    let mut roots = rustls::RootCertStore::empty();
+
+    match &connection.ca_cert {
+        Some(path) => {
+            for cert in CertificateDer::pem_file_iter(path)
+                .with_context(|| format!("failed to read {}", path.display()))?
+            {
+                let cert =
+                    cert.with_context(|| format!("invalid certificate in {}", path.display()))?;
+                roots.add(cert).context("invalid CA certificate")?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let provider = rustls::crypto::aws_lc_rs::default_provider();
+    let builder = rustls::ClientConfig::builder_with_provider(provider.into())
+        .with_safe_default_protocol_versions()
+        .context("failed to configure TLS")?
+        .with_root_certificates(roots);
+
+    let (Some(cert_path), Some(key_path)) = (&connection.client_cert, &connection.client_key)
+    else {
+        return Ok(builder.with_no_client_auth());
+    };
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read {}", cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("failed to read {}", key_path.display()))?;
+
+    builder
+        .with_client_auth_cert(certs, key)
+        .context("invalid client certificate")
+}
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+fn make_native_tls_connector(connection: &Connection) -> Result<native_tls::TlsConnector> {
+    let read = |path: &PathBuf| {
+        fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+    };
+
+    let mut builder = native_tls::TlsConnector::builder();
+
+    if let Some(path) = &connection.ca_cert {
+        for cert in native_tls::Certificate::stack_from_pem(&read(path)?)
+            .with_context(|| format!("invalid certificate in {}", path.display()))?
+        {
+            builder.add_root_certificate(cert);
+        }
+    }
+
+    if let (Some(cert), Some(key)) = (&connection.client_cert, &connection.client_key) {
+        let identity = native_tls::Identity::from_pkcs8(&read(cert)?, &read(key)?)
+            .context("invalid client certificate")?;
+        builder.identity(identity);
+    }
+
+    builder.build().context("failed to configure TLS")
+}
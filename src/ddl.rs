@@ -0,0 +1,683 @@
+use std::{
+    fmt::Write,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    miner::parse_type,
+    options::Options,
+    schema::{Column, DefaultKind, SqlType, Table},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    QuotedIdent(String),
+    Str(String),
+    Number(String),
+    Punct(char),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// Byte offsets of the token in the source.
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = bytes[pos];
+
+        let kind = match c {
+            _ if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            // -- comment
+            b'-' if bytes.get(pos + 1) == Some(&b'-') => {
+                pos = sql[pos..].find('\n').map_or(bytes.len(), |n| pos + n);
+                continue;
+            }
+            // /* comment */
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                let len = sql[pos + 2..].find("*/").context("unterminated comment")?;
+                pos += len + 4;
+                continue;
+            }
+            b'\'' => {
+                let (value, len) = unquote(&sql[pos..], '\'').context("unterminated string")?;
+                pos += len;
+                TokenKind::Str(value)
+            }
+            b'`' | b'"' => {
+                let (value, len) =
+                    unquote(&sql[pos..], c as char).context("unterminated identifier")?;
+                pos += len;
+                TokenKind::QuotedIdent(value)
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_')
+                {
+                    pos += 1;
+                }
+                TokenKind::Ident(sql[start..pos].into())
+            }
+            _ if c.is_ascii_digit() => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'.')
+                {
+                    pos += 1;
+                }
+                TokenKind::Number(sql[start..pos].into())
+            }
+            _ => {
+                let c = sql[pos..].chars().next().expect("not empty");
+                pos += c.len_utf8();
+                TokenKind::Punct(c)
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            start,
+            end: pos,
+        });
+    }
+
+    Ok(tokens)
+}
+
+// Returns the unescaped content of a quoted literal and its length in the source.
+fn unquote(raw: &str, quote: char) -> Option<(String, usize)> {
+    let mut value = String::new();
+    let mut chars = raw.char_indices().skip(1);
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?.1),
+            // Doubled quote is an escaped one.
+            _ if c == quote && raw[idx + 1..].starts_with(quote) => {
+                value.push(quote);
+                chars.next();
+            }
+            _ if c == quote => return Some((value, idx + 1)),
+            _ => value.push(c),
+        }
+    }
+
+    None
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(sql: &'a str) -> Result<Self> {
+        Ok(Self {
+            sql,
+            tokens: tokenize(sql)?,
+            pos: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn next(&mut self) -> Option<&TokenKind> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(&token.kind)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(TokenKind::Ident(ident)) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn eat_keywords(&mut self, keywords: &[&str]) -> bool {
+        let start = self.pos;
+
+        if keywords.iter().all(|k| self.eat_keyword(k)) {
+            return true;
+        }
+
+        self.pos = start;
+        false
+    }
+
+    fn eat_punct(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&TokenKind::Punct(punct));
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<()> {
+        if !self.eat_punct(punct) {
+            bail!("expected `{}`", punct);
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next() {
+            Some(TokenKind::Ident(ident) | TokenKind::QuotedIdent(ident)) => Ok(ident.clone()),
+            _ => bail!("expected an identifier"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.next() {
+            Some(TokenKind::Str(value)) => Ok(value.clone()),
+            _ => bail!("expected a string literal"),
+        }
+    }
+
+    // Skips tokens until one of `stops` at the zero depth, returns the skipped source.
+    fn skip_until(&mut self, stops: &[char], keywords: &[&str]) -> &'a str {
+        let start = self.pos;
+        let mut depth = 0usize;
+
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::Punct(c) if depth == 0 && stops.contains(c) => break,
+                TokenKind::Ident(_)
+                    if depth == 0 && keywords.iter().any(|k| self.is_keyword(k)) =>
+                {
+                    break
+                }
+                TokenKind::Punct('(' | '[') => depth += 1,
+                TokenKind::Punct(')' | ']') => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+
+        self.source(start, self.pos)
+    }
+
+    fn source(&self, from: usize, to: usize) -> &'a str {
+        if from == to {
+            return "";
+        }
+
+        &self.sql[self.tokens[from].start..self.tokens[to - 1].end]
+    }
+
+    fn skip_statement(&mut self) {
+        self.skip_until(&[';'], &[]);
+        self.eat_punct(';');
+    }
+
+    // CREATE [OR REPLACE] TABLE [IF NOT EXISTS] [db.]name [ON CLUSTER cluster] (
+    fn parse_create_table_header(&mut self) -> Option<(Option<String>, String)> {
+        let start = self.pos;
+
+        let found = (self.eat_keyword("CREATE") || self.eat_keyword("ATTACH")) && {
+            self.eat_keywords(&["OR", "REPLACE"]);
+            self.eat_keyword("TEMPORARY");
+            self.eat_keyword("TABLE")
+        };
+
+        if !found {
+            self.pos = start;
+            return None;
+        }
+
+        self.eat_keywords(&["IF", "NOT", "EXISTS"]);
+
+        let mut database = None;
+        let mut name = self.ident().ok()?;
+
+        if self.eat_punct('.') {
+            database = Some(name);
+            name = self.ident().ok()?;
+        }
+
+        if self.eat_keyword("UUID") {
+            self.next();
+        }
+
+        if self.eat_keywords(&["ON", "CLUSTER"]) {
+            self.next();
+        }
+
+        self.is_punct('(').then_some((database, name))
+    }
+
+    fn is_punct(&self, punct: char) -> bool {
+        self.peek() == Some(&TokenKind::Punct(punct))
+    }
+
+    fn parse_columns(&mut self) -> Result<Vec<Column>> {
+        self.expect_punct('(')?;
+
+        let mut columns = Vec::new();
+
+        loop {
+            if self.eat_punct(')') {
+                break;
+            }
+
+            if self.is_keyword("INDEX")
+                || self.is_keyword("PROJECTION")
+                || self.is_keyword("CONSTRAINT")
+                || self.is_keyword("PRIMARY")
+            {
+                self.skip_until(&[',', ')'], &[]);
+            } else {
+                let column = self.parse_column(&columns)?;
+                columns.push(column);
+            }
+
+            if !self.eat_punct(',') {
+                self.expect_punct(')')?;
+                break;
+            }
+        }
+
+        Ok(columns)
+    }
+
+    // name [type] [NULL|NOT NULL] [DEFAULT|MATERIALIZED|ALIAS|EPHEMERAL expr]
+    //      [COMMENT 'comment'] [CODEC(codec)] [TTL expr] [PRIMARY KEY] [SETTINGS (..)]
+    fn parse_column(&mut self, prev: &[Column]) -> Result<Column> {
+        const CLAUSES: &[&str] = &[
+            "COMMENT",
+            "CODEC",
+            "STATISTICS",
+            "TTL",
+            "PRIMARY",
+            "SETTINGS",
+        ];
+
+        let name = self.ident().context("expected a column name")?;
+
+        (|| {
+            let raw_type = self.parse_type()?;
+            let mut column = Column::new(name.clone(), SqlType::String);
+            column.position = prev.len() as u64 + 1;
+
+            let mut nullable = false;
+
+            loop {
+                match self.peek() {
+                    None => bail!("unexpected end of the column list"),
+                    Some(TokenKind::Punct(',' | ')')) => break,
+                    _ => {}
+                }
+
+                if self.eat_keywords(&["NOT", "NULL"]) {
+                    nullable = false;
+                } else if self.eat_keyword("NULL") {
+                    nullable = true;
+                } else if let Some(kind) = self.eat_default_kind() {
+                    column.default_kind = Some(kind);
+                    column.default_expression = self.skip_until(&[',', ')'], CLAUSES).into();
+                } else if self.eat_keyword("COMMENT") {
+                    column.comment = self.string()?;
+                } else if self.is_keyword("CODEC") {
+                    let start = self.pos;
+                    self.next();
+                    self.skip_until(&[',', ')'], CLAUSES);
+                    column.compression_codec = self.source(start, self.pos).into();
+                } else if self.eat_keywords(&["PRIMARY", "KEY"]) {
+                    column.is_in_primary_key = true;
+                } else {
+                    // Other clauses don't affect the generated code.
+                    self.next();
+                    self.skip_until(&[',', ')'], CLAUSES);
+                }
+            }
+
+            column.type_ = if raw_type.is_empty() {
+                infer_type(&column.default_expression, prev)
+                    .context("the type must be specified")?
+            } else {
+                parse_type(&raw_type)
+                    .with_context(|| format!("failed to parse the `{}` type", raw_type))?
+            };
+
+            if nullable && !matches!(column.type_, SqlType::Nullable(_)) {
+                column.type_ = SqlType::Nullable(Box::new(column.type_));
+            }
+
+            Ok(column)
+        })()
+        .with_context(|| format!("failed to handle the `{}` column", name))
+    }
+
+    fn eat_default_kind(&mut self) -> Option<DefaultKind> {
+        [
+            ("DEFAULT", DefaultKind::Default),
+            ("MATERIALIZED", DefaultKind::Materialized),
+            ("ALIAS", DefaultKind::Alias),
+            ("EPHEMERAL", DefaultKind::Ephemeral),
+        ]
+        .into_iter()
+        .find_map(|(keyword, kind)| self.eat_keyword(keyword).then_some(kind))
+    }
+
+    // ENGINE = engine(..) [PARTITION BY expr] [ORDER BY expr] [PRIMARY KEY expr]
+    //                     [SAMPLE BY expr] [TTL expr] [SETTINGS ..] [COMMENT 'comment']
+    fn parse_table_clauses(&mut self, table: &mut Table) -> Result<()> {
+        const CLAUSES: &[&str] = &[
+            "ENGINE",
+            "PARTITION",
+            "ORDER",
+            "PRIMARY",
+            "SAMPLE",
+            "TTL",
+            "SETTINGS",
+            "COMMENT",
+        ];
+
+        let mut engine_args = "";
+        let mut sample_key = "";
+        let mut settings = "";
+
+        while self.peek().is_some() && !self.eat_punct(';') {
+            if self.eat_keyword("ENGINE") {
+                self.eat_punct('=');
+                table.engine = self.ident().context("expected an engine")?;
+                engine_args = self.skip_until(&[';'], CLAUSES);
+            } else if self.eat_keywords(&["PARTITION", "BY"]) {
+                table.partition_key = strip_parens(self.skip_until(&[';'], CLAUSES)).into();
+            } else if self.eat_keywords(&["ORDER", "BY"]) {
+                table.sorting_key = strip_parens(self.skip_until(&[';'], CLAUSES)).into();
+            } else if self.eat_keywords(&["PRIMARY", "KEY"]) {
+                table.primary_key = strip_parens(self.skip_until(&[';'], CLAUSES)).into();
+            } else if self.eat_keywords(&["SAMPLE", "BY"]) {
+                sample_key = self.skip_until(&[';'], CLAUSES);
+            } else if self.eat_keyword("TTL") {
+                table.ttl = self.skip_until(&[';'], CLAUSES).into();
+            } else if self.eat_keyword("SETTINGS") {
+                settings = self.skip_until(&[';'], CLAUSES);
+            } else if self.eat_keyword("COMMENT") {
+                table.comment = self.string()?;
+            } else {
+                // E.g. `AS SELECT ..` of views.
+                self.skip_statement();
+                break;
+            }
+        }
+
+        if table.primary_key.is_empty() {
+            table.primary_key = table.sorting_key.clone();
+        }
+
+        // Mimic `system.tables.engine_full`.
+        let mut engine_full = format!("{}{}", table.engine, engine_args);
+        for (clause, value) in [
+            ("PARTITION BY", table.partition_key.as_str()),
+            ("PRIMARY KEY", table.primary_key.as_str()),
+            ("ORDER BY", table.sorting_key.as_str()),
+            ("SAMPLE BY", sample_key),
+            ("TTL", table.ttl.as_str()),
+            ("SETTINGS", settings),
+        ] {
+            if !value.is_empty() {
+                let _ = write!(engine_full, " {} {}", clause, value);
+            }
+        }
+        table.engine_full = engine_full;
+
+        let partition_key = key_columns(&table.partition_key)?;
+        let sorting_key = key_columns(&table.sorting_key)?;
+        let primary_key = key_columns(&table.primary_key)?;
+
+        for column in &mut table.columns {
+            column.is_in_partition_key = partition_key.contains(&column.name);
+            column.is_in_sorting_key = sorting_key.contains(&column.name);
+            column.is_in_primary_key |= primary_key.contains(&column.name);
+        }
+
+        Ok(())
+    }
+
+    // Reads the type and formats it in the way ClickHouse does.
+    fn parse_type(&mut self) -> Result<String> {
+        const STOPS: &[&str] = &[
+            "NULL",
+            "NOT",
+            "DEFAULT",
+            "MATERIALIZED",
+            "ALIAS",
+            "EPHEMERAL",
+            "COMMENT",
+            "CODEC",
+            "STATISTICS",
+            "TTL",
+            "PRIMARY",
+            "SETTINGS",
+        ];
+
+        let mut type_ = String::new();
+        let mut depth = 0usize;
+
+        while let Some(kind) = self.peek() {
+            if depth == 0 && (STOPS.iter().any(|k| self.is_keyword(k)) || self.is_punct(',')) {
+                break;
+            }
+
+            match kind {
+                TokenKind::Punct(')') if depth == 0 => break,
+                TokenKind::Punct('(') => {
+                    depth += 1;
+                    type_.push('(');
+                }
+                TokenKind::Punct(')') => {
+                    depth -= 1;
+                    type_.push(')');
+                }
+                TokenKind::Punct(',') => type_.push_str(", "),
+                TokenKind::Punct('=') => type_.push_str(" = "),
+                TokenKind::Punct(c) => type_.push(*c),
+                TokenKind::Ident(ident) | TokenKind::QuotedIdent(ident) => {
+                    if type_.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                        type_.push(' ');
+                    }
+                    type_.push_str(ident);
+                }
+                TokenKind::Number(number) => type_.push_str(number),
+                TokenKind::Str(value) => {
+                    type_.push('\'');
+                    type_.push_str(&value.replace('\\', "\\\\").replace('\'', "\\'"));
+                    type_.push('\'');
+                }
+            }
+
+            self.pos += 1;
+        }
+
+        Ok(type_)
+    }
+}
+
+// `(a, b)` -> `a, b`, `tuple()` -> ``.
+fn strip_parens(key: &str) -> &str {
+    if key.eq_ignore_ascii_case("tuple()") {
+        return "";
+    }
+
+    let Some(inner) = key.strip_prefix('(').and_then(|k| k.strip_suffix(')')) else {
+        return key;
+    };
+
+    // Don't strip `(a) + (b)`.
+    let mut depth = 0usize;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return key,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    inner.trim()
+}
+
+fn key_columns(key: &str) -> Result<Vec<String>> {
+    Ok(tokenize(key)?
+        .into_iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::Ident(ident) | TokenKind::QuotedIdent(ident) => Some(ident),
+            _ => None,
+        })
+        .collect())
+}
+
+// Infers the type of a column without the type from its default expression.
+fn infer_type(expr: &str, prev: &[Column]) -> Option<SqlType> {
+    let mut tokens = tokenize(expr).ok()?.into_iter().map(|t| t.kind);
+
+    let (negative, token) = match tokens.next()? {
+        TokenKind::Punct('-') => (true, tokens.next()?),
+        token => (false, token),
+    };
+
+    if tokens.next().is_some() {
+        return None;
+    }
+
+    Some(match token {
+        TokenKind::Ident(ident) | TokenKind::QuotedIdent(ident) if !negative => {
+            match ident.to_lowercase().as_str() {
+                "true" | "false" => SqlType::Bool,
+                _ => prev.iter().find(|c| c.name == ident)?.type_.clone(),
+            }
+        }
+        TokenKind::Str(_) if !negative => SqlType::String,
+        TokenKind::Number(number) => {
+            if let Ok(value) = number.parse::<u64>() {
+                match (negative, value) {
+                    (false, 0..=0xff) => SqlType::UInt8,
+                    (false, 0..=0xffff) => SqlType::UInt16,
+                    (false, 0..=0xffff_ffff) => SqlType::UInt32,
+                    (false, _) => SqlType::UInt64,
+                    (true, 0..=0x80) => SqlType::Int8,
+                    (true, 0..=0x8000) => SqlType::Int16,
+                    (true, 0..=0x8000_0000) => SqlType::Int32,
+                    (true, _) => SqlType::Int64,
+                }
+            } else {
+                number.parse::<f64>().ok()?;
+                SqlType::Float64
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// Returns the target of `CREATE MATERIALIZED VIEW .. TO [db.]table`.
+pub fn parse_mv_target(sql: &str) -> Option<(Option<String>, String)> {
+    let mut parser = Parser::new(sql).ok()?;
+
+    // The column list and the query follow the target.
+    while parser.peek().is_some() && !parser.is_punct('(') && !parser.is_keyword("AS") {
+        if !parser.eat_keyword("TO") {
+            parser.next();
+            continue;
+        }
+
+        let name = parser.ident().ok()?;
+        if !parser.eat_punct('.') {
+            return Some((None, name));
+        }
+
+        return Some((Some(name), parser.ident().ok()?));
+    }
+
+    None
+}
+
+/// Reads `CREATE TABLE` statements from the file, `-` means stdin.
+pub fn read(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut sql = String::new();
+        io::stdin()
+            .read_to_string(&mut sql)
+            .context("failed to read stdin")?;
+        return Ok(sql);
+    }
+
+    fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// Parses `CREATE TABLE` statements and builds the table requested in options.
+///
+/// A statement qualified with another database is taken only if it's the only
+/// one with the table's name.
+pub fn parse(sql: &str, options: &Options) -> Result<Table> {
+    let mut parser = Parser::new(sql)?;
+    let mut candidates = Vec::new();
+
+    while parser.peek().is_some() {
+        let Some((database, name)) = parser.parse_create_table_header() else {
+            parser.skip_statement();
+            continue;
+        };
+
+        if name == options.table {
+            candidates.push((database, parser.pos));
+        }
+
+        parser.skip_statement();
+    }
+
+    let matches = |database: &Option<String>| {
+        database
+            .as_ref()
+            .is_none_or(|database| *database == options.database)
+    };
+
+    let (database, pos) = match &candidates[..] {
+        [] => bail!("there is no `CREATE TABLE {}` statement", options.table),
+        [only] => only.clone(),
+        _ => match candidates.iter().find(|(database, _)| matches(database)) {
+            Some(found) => found.clone(),
+            None => bail!(
+                "there are several `CREATE TABLE {}` statements, but none in the `{}` database",
+                options.table,
+                options.database
+            ),
+        },
+    };
+
+    let mut table = Table {
+        database: database.unwrap_or_else(|| options.database.clone()),
+        name: options.table.clone(),
+        ..Table::default()
+    };
+
+    parser.pos = pos;
+    (|| {
+        table.columns = parser.parse_columns()?;
+        parser.parse_table_clauses(&mut table)
+    })()
+    .with_context(|| format!("failed to parse the `{}` table", table.name))?;
+
+    table.ignore(&options.ignore);
+    Ok(table)
+}
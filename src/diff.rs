@@ -0,0 +1,243 @@
+use std::fmt;
+
+use similar::{Algorithm, DiffTag};
+
+use crate::schema::{Column, SqlType, Table};
+
+/// A difference between two versions of the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Change {
+    pub column: String,
+    pub kind: ChangeKind,
+    /// The effect on the struct generated for the old version.
+    pub compatibility: Compatibility,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChangeKind {
+    Added(SqlType),
+    Removed(SqlType),
+    /// The column is moved relative to other columns, 1-based positions.
+    Reordered {
+        from: u64,
+        to: u64,
+    },
+    Retyped {
+        from: SqlType,
+        to: SqlType,
+    },
+}
+
+/// The effect of the change on the struct generated for the old version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    Compatible,
+    /// Rows can be inserted, but reading them fails.
+    BreaksDeserialization,
+    /// Rows can be read, but inserting them fails.
+    BreaksInsertion,
+    /// Neither reading nor inserting works.
+    BreaksBoth,
+}
+
+impl Compatibility {
+    fn new(breaks_deserialization: bool, breaks_insertion: bool) -> Self {
+        match (breaks_deserialization, breaks_insertion) {
+            (false, false) => Compatibility::Compatible,
+            (true, false) => Compatibility::BreaksDeserialization,
+            (false, true) => Compatibility::BreaksInsertion,
+            (true, true) => Compatibility::BreaksBoth,
+        }
+    }
+
+    pub fn breaks_deserialization(self) -> bool {
+        matches!(
+            self,
+            Compatibility::BreaksDeserialization | Compatibility::BreaksBoth
+        )
+    }
+
+    pub fn breaks_insertion(self) -> bool {
+        matches!(
+            self,
+            Compatibility::BreaksInsertion | Compatibility::BreaksBoth
+        )
+    }
+
+    pub fn is_breaking(self) -> bool {
+        self != Compatibility::Compatible
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self::new(
+            self.breaks_deserialization() || other.breaks_deserialization(),
+            self.breaks_insertion() || other.breaks_insertion(),
+        )
+    }
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compatibility::Compatible => "compatible",
+            Compatibility::BreaksDeserialization => "breaks deserialization",
+            Compatibility::BreaksInsertion => "breaks insertion",
+            Compatibility::BreaksBoth => "breaks deserialization and insertion",
+        })
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ChangeKind::Added(type_) => write!(f, "added `{}` {}", self.column, type_),
+            ChangeKind::Removed(type_) => write!(f, "removed `{}` {}", self.column, type_),
+            ChangeKind::Reordered { from, to } => {
+                write!(f, "moved `{}` from {} to {}", self.column, from, to)
+            }
+            ChangeKind::Retyped { from, to } => {
+                write!(f, "retyped `{}` from {} to {}", self.column, from, to)
+            }
+        }
+    }
+}
+
+/// Compares columns of two versions of the table.
+///
+/// Removed columns come first, then other changes in the order of the new
+/// version. Rows are expected to be read and inserted with explicit lists of
+/// columns (`?fields`), as the `clickhouse` crate does, so reordering is
+/// compatible and added columns are filled with their defaults on insertion.
+pub fn diff(old: &Table, new: &Table) -> Vec<Change> {
+    let find = |table: &'_ Table, name: &str| {
+        table
+            .columns
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.type_.clone())
+    };
+
+    let mut changes = Vec::new();
+
+    for column in &old.columns {
+        if find(new, &column.name).is_none() {
+            changes.push(Change {
+                column: column.name.clone(),
+                kind: ChangeKind::Removed(column.type_.clone()),
+                compatibility: Compatibility::BreaksBoth,
+            });
+        }
+    }
+
+    let moved = find_moved(old, new);
+
+    for column in &new.columns {
+        let Some(old_type) = find(old, &column.name) else {
+            changes.push(Change {
+                column: column.name.clone(),
+                kind: ChangeKind::Added(column.type_.clone()),
+                compatibility: Compatibility::Compatible,
+            });
+            continue;
+        };
+
+        if let Some(old_column) = moved.iter().find(|c| c.name == column.name) {
+            changes.push(Change {
+                column: column.name.clone(),
+                kind: ChangeKind::Reordered {
+                    from: old_column.position,
+                    to: column.position,
+                },
+                compatibility: Compatibility::Compatible,
+            });
+        }
+
+        if old_type != column.type_ {
+            changes.push(Change {
+                column: column.name.clone(),
+                compatibility: compare_types(&old_type, &column.type_),
+                kind: ChangeKind::Retyped {
+                    from: old_type,
+                    to: column.type_.clone(),
+                },
+            });
+        }
+    }
+
+    changes
+}
+
+// Returns old columns moved relative to others, the minimal set of them is
+// found by diffing names of columns present in both versions.
+fn find_moved<'a>(old: &'a Table, new: &Table) -> Vec<&'a Column> {
+    let old_common = old
+        .columns
+        .iter()
+        .filter(|c| new.columns.iter().any(|n| n.name == c.name))
+        .collect::<Vec<_>>();
+
+    let old_names = old_common.iter().map(|c| &c.name).collect::<Vec<_>>();
+    let new_names = new
+        .columns
+        .iter()
+        .map(|c| &c.name)
+        .filter(|name| old_names.contains(name))
+        .collect::<Vec<_>>();
+
+    similar::capture_diff_slices(Algorithm::Myers, &old_names, &new_names)
+        .iter()
+        .filter(|op| matches!(op.tag(), DiffTag::Delete | DiffTag::Replace))
+        .flat_map(|op| op.old_range())
+        .map(|idx| old_common[idx])
+        .collect()
+}
+
+// Values of the old type are read from and written to a column of the new type.
+pub(crate) fn compare_types(old: &SqlType, new: &SqlType) -> Compatibility {
+    use SqlType::*;
+
+    match (old, new) {
+        _ if old == new => Compatibility::Compatible,
+        // The timezone doesn't affect the representation.
+        (DateTime(_), DateTime(_)) => Compatibility::Compatible,
+        (DateTime64(old, _), DateTime64(new, _)) if old == new => Compatibility::Compatible,
+        // Values fit as long as the size and the scale are the same.
+        (Decimal(old_prec, old_scale), Decimal(new_prec, new_scale))
+            if old_scale == new_scale && decimal_size(*old_prec) == decimal_size(*new_prec) =>
+        {
+            Compatibility::new(false, new_prec < old_prec)
+        }
+        (Enum8(old), Enum8(new)) | (Enum16(old), Enum16(new)) => Compatibility::new(
+            new.iter().any(|(_, v)| !has_value(old, *v)),
+            old.iter().any(|(_, v)| !has_value(new, *v)),
+        ),
+        (Array(old), Array(new)) | (Nullable(old), Nullable(new)) => compare_types(old, new),
+        (Map(old_key, old_value), Map(new_key, new_value)) => {
+            compare_types(old_key, new_key).merge(compare_types(old_value, new_value))
+        }
+        (Tuple(old), Tuple(new)) if old.len() == new.len() => old
+            .iter()
+            .zip(new)
+            .fold(Compatibility::Compatible, |acc, (old, new)| {
+                acc.merge(compare_types(old, new))
+            }),
+        _ => Compatibility::BreaksBoth,
+    }
+}
+
+// Rust enums are (de)serialized by values, names of variants don't matter.
+fn has_value(variants: &[(String, i32)], value: i32) -> bool {
+    variants.iter().any(|(_, v)| *v == value)
+}
+
+// Decimals are stored as 32, 64, 128 or 256 bit integers depending on the precision.
+fn decimal_size(precision: u32) -> u32 {
+    match precision {
+        0..=9 => 32,
+        10..=18 => 64,
+        19..=38 => 128,
+        _ => 256,
+    }
+}
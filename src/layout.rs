@@ -0,0 +1,568 @@
+use std::fmt::Write;
+
+use quote::{format_ident, quote};
+use syn::{punctuated::Punctuated, visit_mut::VisitMut, Token};
+
+use crate::codegen::unparse_one;
+
+// Default settings of `rustfmt`.
+const MAX_WIDTH: usize = 100;
+const FN_CALL_WIDTH: usize = 60;
+const ARRAY_WIDTH: usize = 60;
+const STRUCT_LIT_WIDTH: usize = 18;
+const SHORT_ARRAY_ELEMENT_WIDTH: usize = 10;
+
+const DERIVE_MARKER: &str = "#[__ch2rs_derive_";
+const SIGNATURE_MARKER: &str = "fn __ch2rs_signature_";
+const TYPE_MARKER: &str = "__ch2rs_type_";
+const VALUE_MARKER: &str = "__ch2rs_value_";
+
+/// Lays out parts of the code the way `rustfmt` does.
+///
+/// `prettyplease` wraps lines at a narrower width and by other rules, so
+/// derives, signatures, types of fields and values of constants are replaced
+/// by markers before printing and laid out from the syntax tree afterwards.
+#[derive(Default)]
+pub(crate) struct Layout {
+    derives: Vec<Vec<String>>,
+    signatures: Vec<(syn::Visibility, syn::Signature)>,
+    types: Vec<syn::Type>,
+    values: Vec<syn::Expr>,
+}
+
+impl VisitMut for Layout {
+    fn visit_attribute_mut(&mut self, attr: &mut syn::Attribute) {
+        if !attr.path().is_ident("derive") {
+            return;
+        }
+
+        let Ok(paths) = attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+        else {
+            return;
+        };
+
+        let marker = format_ident!("__ch2rs_derive_{}", self.derives.len());
+        self.derives
+            .push(paths.iter().map(path_to_string).collect());
+        *attr = syn::parse_quote!(#[#marker]);
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, item: &mut syn::ImplItemFn) {
+        syn::visit_mut::visit_impl_item_fn_mut(self, item);
+
+        let marker = format_ident!("__ch2rs_signature_{}", self.signatures.len());
+        let vis = std::mem::replace(&mut item.vis, syn::Visibility::Inherited);
+        let sig = std::mem::replace(&mut item.sig, syn::parse_quote!(fn #marker()));
+        self.signatures.push((vis, sig));
+    }
+
+    fn visit_field_mut(&mut self, field: &mut syn::Field) {
+        if field.ident.is_none() {
+            return;
+        }
+
+        let marker = format_ident!("__ch2rs_type_{}", self.types.len());
+        let ty = std::mem::replace(&mut field.ty, syn::parse_quote!(#marker));
+        self.types.push(ty);
+    }
+
+    fn visit_impl_item_const_mut(&mut self, item: &mut syn::ImplItemConst) {
+        self.replace_value(&mut item.expr);
+    }
+
+    fn visit_item_const_mut(&mut self, item: &mut syn::ItemConst) {
+        self.replace_value(&mut item.expr);
+    }
+}
+
+impl Layout {
+    fn replace_value(&mut self, expr: &mut syn::Expr) {
+        if !is_supported(expr) {
+            return;
+        }
+
+        let marker = format_ident!("__ch2rs_value_{}", self.values.len());
+        let value = std::mem::replace(expr, syn::parse_quote!(#marker));
+        self.values.push(value);
+    }
+
+    /// Replaces markers in the printed code.
+    pub(crate) fn restore(&self, code: &str) -> String {
+        let mut result = String::with_capacity(code.len());
+
+        for line in code.lines() {
+            let trimmed = line.trim_start();
+            let indent = &line[..line.len() - trimmed.len()];
+
+            let derive = (trimmed.strip_prefix(DERIVE_MARKER))
+                .and_then(|rest| rest.strip_suffix(']')?.parse::<usize>().ok())
+                .and_then(|idx| self.derives.get(idx));
+            let signature = (trimmed.strip_prefix(SIGNATURE_MARKER))
+                .and_then(|rest| rest.strip_suffix("() {")?.parse::<usize>().ok())
+                .and_then(|idx| self.signatures.get(idx));
+            let type_ = (trimmed.strip_suffix(','))
+                .and_then(|rest| rest.split_once(": "))
+                .and_then(|(name, marker)| {
+                    let idx = marker.strip_prefix(TYPE_MARKER)?.parse::<usize>().ok()?;
+                    Some((name, self.types.get(idx)?))
+                });
+            let value = (trimmed.strip_suffix(';'))
+                .and_then(|rest| rest.split_once(" = "))
+                .and_then(|(lhs, marker)| {
+                    let idx = marker.strip_prefix(VALUE_MARKER)?.parse::<usize>().ok()?;
+                    Some((lhs, self.values.get(idx)?))
+                });
+
+            if let Some(paths) = derive {
+                layout_derive(&mut result, indent, paths);
+            } else if let Some((vis, sig)) = signature {
+                layout_signature(&mut result, indent, vis, sig);
+            } else if let Some((name, ty)) = type_ {
+                let layout = |indent, width| layout_type(ty, indent, width);
+                let fallback = || print_type(ty);
+                layout_rhs(
+                    &mut result,
+                    indent,
+                    &format!("{}:", name),
+                    ",",
+                    layout,
+                    fallback,
+                );
+            } else if let Some((lhs, expr)) = value {
+                let layout = |indent, width| layout_expr(expr, indent, width);
+                let fallback = || print_expr(expr);
+                layout_rhs(
+                    &mut result,
+                    indent,
+                    &format!("{} =", lhs),
+                    ";",
+                    layout,
+                    fallback,
+                );
+            } else {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+
+        result
+    }
+}
+
+fn path_to_string(path: &syn::Path) -> String {
+    let segments = path.segments.iter().map(|s| s.ident.to_string());
+    let joined = segments.collect::<Vec<_>>().join("::");
+
+    match path.leading_colon {
+        Some(_) => format!("::{}", joined),
+        None => joined,
+    }
+}
+
+// On one line if it fits, then with all paths on the next line, then vertically.
+// `rustfmt` keeps the one-line form 4 columns shorter than `MAX_WIDTH`.
+fn layout_derive(dst: &mut String, indent: &str, paths: &[String]) {
+    let list = paths.join(", ");
+
+    if indent.len() + "#[derive()]".len() + list.len() + 4 <= MAX_WIDTH {
+        let _ = writeln!(dst, "{}#[derive({})]", indent, list);
+    } else if indent.len() + 4 + list.len() <= MAX_WIDTH {
+        let _ = writeln!(
+            dst,
+            "{}#[derive(\n{}    {},\n{})]",
+            indent, indent, list, indent
+        );
+    } else {
+        let _ = writeln!(dst, "{}#[derive(", indent);
+
+        for path in paths {
+            let _ = writeln!(dst, "{}    {},", indent, path);
+        }
+
+        let _ = writeln!(dst, "{})]", indent);
+    }
+}
+
+// On one line if it fits, otherwise every argument is on its own line, as
+// `prettyplease` prints it.
+fn layout_signature(dst: &mut String, indent: &str, vis: &syn::Visibility, sig: &syn::Signature) {
+    let item: syn::Item = syn::parse_quote!(#vis #sig {});
+    let printed = unparse_one(Vec::new(), vec![item]);
+    let printed = printed.trim_end().strip_suffix(" {}").unwrap_or(&printed);
+
+    let lines = printed.lines().collect::<Vec<_>>();
+    let oneline = match &lines[..] {
+        [first, args @ .., last] => {
+            let args = args.iter().map(|arg| arg.trim().trim_end_matches(','));
+            format!("{}{}{}", first, args.collect::<Vec<_>>().join(", "), last)
+        }
+        _ => printed.to_string(),
+    };
+
+    if indent.len() + oneline.len() + " {".len() <= MAX_WIDTH {
+        let _ = writeln!(dst, "{}{} {{", indent, oneline);
+    } else {
+        for line in lines {
+            let _ = writeln!(dst, "{}{}", indent, line);
+        }
+
+        dst.truncate(dst.len() - 1);
+        dst.push_str(" {\n");
+    }
+}
+
+// Lays out the right-hand side of `lhs` terminated by `end` on the same line if
+// it fits there on one line, otherwise on the next line if it fits there on one
+// line or takes fewer lines. If it doesn't fit anywhere, `rustfmt` leaves the
+// code as is, so it's printed by `fallback`.
+fn layout_rhs(
+    dst: &mut String,
+    indent: &str,
+    lhs: &str,
+    end: &str,
+    layout: impl Fn(usize, usize) -> Option<String>,
+    fallback: impl Fn() -> String,
+) {
+    let width = MAX_WIDTH.saturating_sub(indent.len() + end.len());
+    let same =
+        (width.checked_sub(lhs.len() + " ".len())).and_then(|width| layout(indent.len(), width));
+
+    let next_indent = indent.len() + 4;
+    let next = layout(
+        next_indent,
+        MAX_WIDTH.saturating_sub(next_indent + end.len()),
+    );
+
+    let first_line_ends_with = |s: &str, c| s.lines().next().is_some_and(|l| l.ends_with(c));
+    let prefer_next = |same: &str, next: &str| {
+        !next.contains('\n')
+            || same.lines().count() > next.lines().count() + 1
+            || ['(', '{', '[']
+                .into_iter()
+                .any(|c| first_line_ends_with(same, c) && !first_line_ends_with(next, c))
+    };
+
+    let rhs = match (same, next) {
+        (Some(same), Some(next)) if same.contains('\n') && prefer_next(&same, &next) => {
+            format!("\n{:next_indent$}{}", "", next)
+        }
+        (Some(same), _) => format!(" {}", same),
+        (None, Some(next)) => format!("\n{:next_indent$}{}", "", next),
+        (None, None) => format!(" {}", fallback().replace('\n', &format!("\n{}", indent))),
+    };
+
+    let _ = writeln!(dst, "{}{}{}{}", indent, lhs, rhs, end);
+}
+
+// Returns `None` if the type doesn't fit into `width` columns of the current
+// line, continuation lines are indented by `indent`.
+fn layout_type(ty: &syn::Type, indent: usize, width: usize) -> Option<String> {
+    match ty {
+        syn::Type::Reference(ty) => {
+            let lifetime = ty.lifetime.as_ref().map(|l| format!("{} ", l));
+            let mutability = if ty.mutability.is_some() { "mut " } else { "" };
+            let prefix = format!("&{}{}", lifetime.unwrap_or_default(), mutability);
+            let elem = layout_type(&ty.elem, indent, width.checked_sub(prefix.len())?)?;
+            Some(prefix + &elem)
+        }
+        syn::Type::Tuple(ty) => {
+            let elems = ty.elems.iter().collect::<Vec<_>>();
+            let list = List {
+                delims: ("(", ")"),
+                limit: FN_CALL_WIDTH,
+                overflow: matches!(&elems[..], [elem] if is_overflowable_type(elem)),
+                mixed: false,
+                tuple: true,
+            };
+            list.layout(&elems, indent, width, |elem, indent, width| {
+                layout_type(elem, indent, width)
+            })
+        }
+        syn::Type::Path(ty) if ty.qself.is_none() => {
+            let mut result = String::new();
+
+            for (idx, segment) in ty.path.segments.iter().enumerate() {
+                if idx > 0 || ty.path.leading_colon.is_some() {
+                    result.push_str("::");
+                }
+                result.push_str(&segment.ident.to_string());
+
+                let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+                    continue;
+                };
+
+                if result.contains('\n') {
+                    return None;
+                }
+
+                let args = args.args.iter().collect::<Vec<_>>();
+                let list = List {
+                    delims: ("<", ">"),
+                    limit: MAX_WIDTH,
+                    overflow: matches!(
+                        &args[..],
+                        [syn::GenericArgument::Type(ty)] if is_overflowable_type(ty)
+                    ),
+                    mixed: false,
+                    tuple: false,
+                };
+                let width = width.checked_sub(result.len())?;
+                result += &list.layout(&args, indent, width, |arg, indent, width| match arg {
+                    syn::GenericArgument::Type(ty) => layout_type(ty, indent, width),
+                    arg => Some(quote!(#arg).to_string()).filter(|arg| arg.len() <= width),
+                })?;
+            }
+
+            let first_line = result.lines().next().unwrap_or_default();
+            (first_line.len() <= width).then_some(result)
+        }
+        ty => Some(print_type(ty)).filter(|ty| !ty.contains('\n') && ty.len() <= width),
+    }
+}
+
+fn is_overflowable_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Tuple(_) => true,
+        syn::Type::Reference(ty) => is_overflowable_type(&ty.elem),
+        _ => false,
+    }
+}
+
+fn print_type(ty: &syn::Type) -> String {
+    let alias: syn::Item = syn::parse_quote!(type __Ch2rsType = #ty;);
+    let printed = unparse_one(Vec::new(), vec![alias]);
+    let printed = printed.trim_end().trim_start_matches("type __Ch2rsType = ");
+    printed.trim_end_matches(';').to_string()
+}
+
+// Only expressions generated for constants are supported.
+fn is_supported(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(_) => true,
+        syn::Expr::Path(expr) => expr.qself.is_none(),
+        syn::Expr::Reference(expr) => is_supported(&expr.expr),
+        syn::Expr::Array(expr) => expr.elems.iter().all(is_supported),
+        syn::Expr::Call(expr) => is_supported(&expr.func) && expr.args.iter().all(is_supported),
+        syn::Expr::Struct(expr) => {
+            expr.qself.is_none()
+                && expr.rest.is_none()
+                && expr.fields.iter().all(|field| {
+                    matches!(field.member, syn::Member::Named(_)) && is_supported(&field.expr)
+                })
+        }
+        _ => false,
+    }
+}
+
+fn is_simple(expr: &syn::Expr) -> bool {
+    matches!(expr, syn::Expr::Lit(_) | syn::Expr::Path(_))
+}
+
+fn is_overflowable_expr(expr: &syn::Expr) -> bool {
+    matches!(
+        expr,
+        syn::Expr::Struct(_) | syn::Expr::Call(_) | syn::Expr::Array(_)
+    )
+}
+
+// Returns `None` if the expression doesn't fit into `width` columns of the
+// current line, continuation lines are indented by `indent`.
+fn layout_expr(expr: &syn::Expr, indent: usize, width: usize) -> Option<String> {
+    match expr {
+        syn::Expr::Lit(expr) => {
+            let lit = &expr.lit;
+            Some(quote!(#lit).to_string()).filter(|lit| lit.len() <= width)
+        }
+        syn::Expr::Path(expr) => {
+            Some(path_to_string(&expr.path)).filter(|path| path.len() <= width)
+        }
+        syn::Expr::Reference(expr) => {
+            let prefix = if expr.mutability.is_some() {
+                "&mut "
+            } else {
+                "&"
+            };
+            let inner = layout_expr(&expr.expr, indent, width.checked_sub(prefix.len())?)?;
+            Some(format!("{}{}", prefix, inner))
+        }
+        syn::Expr::Array(expr) => {
+            let elems = expr.elems.iter().collect::<Vec<_>>();
+            let list = List {
+                delims: ("[", "]"),
+                limit: ARRAY_WIDTH,
+                overflow: matches!(&elems[..], [elem] if is_overflowable_expr(elem)),
+                mixed: elems.iter().all(|elem| is_simple(elem)),
+                tuple: false,
+            };
+            list.layout(&elems, indent, width, |elem, indent, width| {
+                layout_expr(elem, indent, width)
+            })
+        }
+        syn::Expr::Call(expr) => {
+            let func = layout_expr(&expr.func, indent, width)?;
+            let args = expr.args.iter().collect::<Vec<_>>();
+            let list = List {
+                delims: ("(", ")"),
+                limit: FN_CALL_WIDTH,
+                overflow: matches!(&args[..], [arg] if is_overflowable_expr(arg)),
+                mixed: false,
+                tuple: false,
+            };
+            let width = width.checked_sub(func.len())?;
+            let args = list.layout(&args, indent, width, |arg, indent, width| {
+                layout_expr(arg, indent, width)
+            })?;
+            Some(func + &args)
+        }
+        syn::Expr::Struct(expr) => layout_struct(expr, indent, width),
+        _ => None,
+    }
+}
+
+// Fields are on one line if they are not wider than `STRUCT_LIT_WIDTH`,
+// otherwise every field is on its own line, its value is moved to the next line
+// if it doesn't fit.
+fn layout_struct(expr: &syn::ExprStruct, indent: usize, width: usize) -> Option<String> {
+    let path = path_to_string(&expr.path);
+    let nested = indent + 4;
+    let nested_width = MAX_WIDTH.checked_sub(nested + ",".len())?;
+
+    let fields = (expr.fields.iter())
+        .map(|field| {
+            let syn::Member::Named(name) = &field.member else {
+                return None;
+            };
+
+            let same = (nested_width.checked_sub(name.to_string().len() + ": ".len()))
+                .and_then(|width| layout_expr(&field.expr, nested, width));
+
+            if let Some(value) = same {
+                return Some(format!("{}: {}", name, value));
+            }
+
+            // The comma isn't taken into account here.
+            let next = layout_expr(&field.expr, nested + 4, MAX_WIDTH.checked_sub(nested + 4)?)?;
+            Some(format!(
+                "{}:\n{:width$}{}",
+                name,
+                "",
+                next,
+                width = nested + 4
+            ))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let oneline = fields.join(", ");
+    if fields.is_empty() {
+        return Some(format!("{} {{}}", path)).filter(|s| s.len() <= width);
+    }
+
+    if !oneline.contains('\n')
+        && oneline.len() <= STRUCT_LIT_WIDTH
+        && path.len() + " {  }".len() + oneline.len() <= width
+    {
+        return Some(format!("{} {{ {} }}", path, oneline));
+    }
+
+    if path.len() + " {".len() > width {
+        return None;
+    }
+
+    let mut result = format!("{} {{\n", path);
+    for field in fields {
+        let _ = writeln!(result, "{:nested$}{},", "", field);
+    }
+    let _ = write!(result, "{:indent$}}}", "");
+    Some(result)
+}
+
+fn print_expr(expr: &syn::Expr) -> String {
+    let item: syn::Item = syn::parse_quote!(const _: () = #expr;);
+    let printed = unparse_one(Vec::new(), vec![item]);
+    let printed = printed.trim_end().trim_start_matches("const _: () = ");
+    printed.trim_end_matches(';').to_string()
+}
+
+struct List {
+    delims: (&'static str, &'static str),
+    // The width of items to put them on one line.
+    limit: usize,
+    // The only item may stay on the line of delimiters, e.g. `Vec<(` and `)>`.
+    overflow: bool,
+    // Short items may share lines, if they don't fit on one line.
+    mixed: bool,
+    // A tuple of one element requires a trailing comma.
+    tuple: bool,
+}
+
+impl List {
+    // On one line if it fits and items are not wider than `limit`, otherwise
+    // every item is on its own line with a trailing comma.
+    fn layout<T>(
+        &self,
+        items: &[T],
+        indent: usize,
+        width: usize,
+        layout: impl Fn(&T, usize, usize) -> Option<String>,
+    ) -> Option<String> {
+        let (open, close) = self.delims;
+        // `rustfmt` doesn't take the trailing comma of a tuple into account.
+        let trailing = if self.tuple && items.len() == 1 {
+            ","
+        } else {
+            ""
+        };
+
+        if self.overflow {
+            let overflowed = (width.checked_sub(open.len() + close.len()))
+                .and_then(|width| layout(&items[0], indent, width));
+
+            if let Some(item) = overflowed {
+                return Some(format!("{}{}{}{}", open, item, trailing, close));
+            }
+        }
+
+        let nested = indent + 4;
+        let nested_width = MAX_WIDTH.checked_sub(nested + ",".len())?;
+        let items = (items.iter())
+            .map(|item| layout(item, nested, nested_width))
+            .collect::<Option<Vec<_>>>()?;
+        let oneline = items.join(", ");
+
+        // The limit doesn't apply to a single item.
+        if !oneline.contains('\n')
+            && (items.len() == 1 || oneline.len() <= self.limit)
+            && open.len() + oneline.len() + close.len() <= width
+        {
+            return Some(format!("{}{}{}{}", open, oneline, trailing, close));
+        }
+
+        if open.len() > width {
+            return None;
+        }
+
+        let mixed = self.mixed
+            && items
+                .iter()
+                .all(|item| item.len() <= SHORT_ARRAY_ELEMENT_WIDTH);
+        let mut result = open.to_string();
+        let mut line_len = 0;
+
+        for item in items {
+            let item_len = item.len() + ",".len();
+
+            if mixed && line_len > 0 && line_len + " ".len() + item_len <= nested_width {
+                result.push(' ');
+                line_len += " ".len() + item_len;
+            } else {
+                let _ = write!(result, "\n{:nested$}", "");
+                line_len = item_len;
+            }
+
+            let _ = write!(result, "{},", item);
+        }
+
+        let _ = write!(result, "\n{:indent$}{}", "", close);
+        Some(result)
+    }
+}
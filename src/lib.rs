@@ -1,14 +1,360 @@
-use anyhow::Result;
+//! Generates Rust structs from ClickHouse schemas for the `clickhouse` crate.
+//!
+//! Besides the CLI, the crate can be used as a library:
+//! * [`generate`] loads the table from the server or DDL and generates code.
+//! * [`mine`] fetches the table's [`schema::Table`] only.
+//! * [`generate_from_table`] generates code for a manually built table.
+//! * [`dump`] fetches tables' schemas to a [`Snapshot`].
+//! * [`diff`] compares the table's schema in two sources.
+//! * [`check`] compares generated code with a file.
+//! * [`regen`] regenerates a file using options from its header.
+//! * [`generate_target`] generates a file configured in [`Config`].
+//! * [`Watcher`] regenerates targets when their schemas change.
+//! * [`Builder`] generates code from build scripts.
 
-pub use options::Options;
+use std::{fs, path::Path};
 
+use anyhow::{bail, Context, Result};
+use clickhouse::Client;
+
+pub use builder::Builder;
+pub use config::{Config, TableConfig, Target};
+pub use diff::{Change, ChangeKind, Compatibility};
+pub use miner::parse_type;
+pub use options::{
+    Connection, DiffOptions, DumpOptions, Options, Override, RegenOptions, SchemaSource,
+    StringRepr, Type,
+};
+pub use output::WriteStatus;
+pub use snapshot::Snapshot;
+pub use watch::Watcher;
+
+mod builder;
 mod codegen;
+mod config;
+mod connection;
+mod ddl;
+mod diff;
+mod layout;
 mod miner;
 mod options;
-mod schema;
+mod output;
+pub mod schema;
+mod snapshot;
+mod watch;
 
+/// Fetches the table's schema from the server.
+pub async fn mine(options: &Options) -> Result<schema::Table> {
+    let client = miner::make_client(&options.connection)?;
+    miner::with_timeout(&options.connection, miner::mine(&client, options)).await
+}
+
+/// Loads the table's schema and generates code for it.
+///
+/// The schema is parsed from [`Options::ddl`] or [`Options::schema_file`] if
+/// one of them is set, otherwise it's fetched from the server. With
+/// [`Options::query`] the row describes the query's result instead and
+/// a `Params` struct is generated for the query's parameters.
+///
+/// Distributed tables are checked against their local tables. With
+/// [`Options::follow_mv`] rows of the materialized view's query and its
+/// target table are generated, with [`Options::dictionary`] the `Key` struct
+/// of the dictionary is generated along with the row. With
+/// [`Options::compatible_with`] the row works with both versions of the table.
 pub async fn generate(options: Options) -> Result<String> {
-    let table = miner::mine(&options).await?;
+    generate_with(options, None).await
+}
+
+// The client is made from options if it isn't provided, e.g. by `Watcher`.
+async fn generate_with(mut options: Options, client: Option<&Client>) -> Result<String> {
+    // Ask for the password once, not for every query.
+    if options.connection.ask_password {
+        options.connection = options.connection.resolve()?;
+    }
+
+    let make_client = || match client {
+        Some(client) => Ok(client.clone()),
+        None => miner::make_client(&options.connection),
+    };
+
+    if options.query.is_some() || options.query_file.is_some() {
+        let client = make_client()?;
+        let mut queries = Vec::new();
+
+        for (module, query) in read_queries(&options)? {
+            let mined = miner::mine_query(&client, &options, &query);
+            let table = miner::with_timeout(&options.connection, mined)
+                .await
+                .with_context(|| match &module {
+                    Some(module) => format!("failed to handle the `{}` query", module),
+                    None => "failed to handle the query".into(),
+                })?;
+
+            queries.push(codegen::Unit {
+                module,
+                query: Some(query),
+                table,
+            });
+        }
+
+        return codegen::generate_units(&queries, &options);
+    }
+
+    if options.follow_mv {
+        let client = make_client()?;
+        let mined = miner::mine_mv(&client, &options);
+        let mv = miner::with_timeout(&options.connection, mined).await?;
+        let units = [
+            codegen::Unit {
+                module: Some("source".into()),
+                query: Some(mv.query),
+                table: mv.source,
+            },
+            codegen::Unit {
+                module: Some("target".into()),
+                query: None,
+                table: mv.target,
+            },
+        ];
+
+        return codegen::generate_units(&units, &options);
+    }
+
+    if options.dictionary {
+        let client = make_client()?;
+        let mined = miner::mine_dictionary(&client, &options);
+        let dictionary = miner::with_timeout(&options.connection, mined).await?;
+        return codegen::generate_dictionary(&dictionary.key, &dictionary.row, &options);
+    }
+
+    let table = load(&options, client).await?;
+
+    if let Some(source) = &options.compatible_with {
+        let other = load_source(&options, source).await?;
+        return codegen::generate_compatible(&table, &other, &options);
+    }
+
     let code = codegen::generate(&table, &options)?;
     Ok(code)
 }
+
+// Returns queries with names of their modules, if `--query-file` is a directory,
+// every `.sql` file in it is placed in a separate module.
+fn read_queries(options: &Options) -> Result<Vec<(Option<String>, String)>> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+    };
+
+    let path = match (&options.query, &options.query_file) {
+        (Some(query), _) => return Ok(vec![(None, query.clone())]),
+        (None, Some(path)) => path,
+        (None, None) => return Ok(Vec::new()),
+    };
+
+    if !path.is_dir() {
+        return Ok(vec![(None, read(path)?)]);
+    }
+
+    let mut paths = fs::read_dir(path)
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+
+    paths.retain(|p| p.extension().is_some_and(|ext| ext == "sql"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let module = path
+                .file_stem()
+                .expect("has an extension")
+                .to_string_lossy();
+            Ok((Some(module.into_owned()), read(path)?))
+        })
+        .collect()
+}
+
+// The client is made from options if it isn't provided and required.
+async fn load(options: &Options, client: Option<&Client>) -> Result<schema::Table> {
+    if options.table.is_empty() {
+        bail!("the table or the query must be specified");
+    } else if let Some(path) = &options.ddl {
+        ddl::parse(&ddl::read(path)?, options)
+    } else if let Some(path) = &options.schema_file {
+        snapshot::load(path, options)
+    } else if let Some(client) = client {
+        miner::with_timeout(&options.connection, miner::mine(client, options)).await
+    } else {
+        let client = miner::make_client(&options.connection)?;
+        miner::with_timeout(&options.connection, miner::mine(&client, options)).await
+    }
+}
+
+/// Fetches schemas of the provided tables from the server.
+pub async fn dump(options: &DumpOptions) -> Result<Snapshot> {
+    let connection = options.connection.resolve()?;
+    let client = miner::make_client(&connection)?;
+    let mut tables = Vec::with_capacity(options.tables.len());
+
+    for table in &options.tables {
+        let (database, name) = table.split_once('.').unwrap_or((&options.database, table));
+
+        let options = Options {
+            connection: connection.clone(),
+            database: database.into(),
+            table: name.into(),
+            ..Options::default()
+        };
+
+        let mined = miner::mine(&client, &options);
+        tables.push(miner::with_timeout(&connection, mined).await?);
+    }
+
+    Ok(Snapshot::new(tables))
+}
+
+/// Loads the table from both sources and compares their columns.
+///
+/// Changes are classified by their effect on the struct generated from
+/// [`DiffOptions::old`].
+pub async fn diff(options: &DiffOptions) -> Result<Vec<Change>> {
+    let base = Options {
+        connection: options.connection.resolve()?,
+        database: options.database.clone(),
+        table: options.table.clone(),
+        ignore: options.ignore.clone(),
+        ..Options::default()
+    };
+
+    let old = load_source(&base, &options.old).await?;
+    let new = load_source(&base, &options.new).await?;
+    Ok(diff_tables(&old, &new))
+}
+
+// Loads the table from the source instead of the one set in options.
+async fn load_source(options: &Options, source: &SchemaSource) -> Result<schema::Table> {
+    let mut options = Options {
+        ddl: None,
+        schema_file: None,
+        ..options.clone()
+    };
+
+    match source {
+        SchemaSource::Server(url) => {
+            if let Some(url) = url {
+                options.connection.url = Some(url.clone());
+            }
+        }
+        SchemaSource::Ddl(path) => options.ddl = Some(path.clone()),
+        SchemaSource::Snapshot(path) => options.schema_file = Some(path.clone()),
+    }
+
+    load(&options, None)
+        .await
+        .with_context(|| format!("failed to load `{}`", source))
+}
+
+/// Compares columns of two versions of the table, no network is required.
+pub fn diff_tables(old: &schema::Table, new: &schema::Table) -> Vec<Change> {
+    diff::diff(old, new)
+}
+
+/// Compares the generated code with the file's content.
+///
+/// Fails with a unified diff if they differ, the error tells whether
+/// the schema fingerprint has changed as well.
+pub fn check(path: &Path, code: &str) -> Result<()> {
+    let actual =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    if actual.trim_end() == code.trim_end() {
+        return Ok(());
+    }
+
+    let reason = match (
+        codegen::parse_fingerprint(&actual),
+        codegen::parse_fingerprint(code),
+    ) {
+        (Some(actual), Some(expected)) if actual != expected => "the schema has changed",
+        _ => "the code has changed",
+    };
+
+    let path = path.display().to_string();
+    let diff = similar::TextDiff::from_lines(actual.trim_end(), code.trim_end())
+        .unified_diff()
+        .header(&path, "generated")
+        .to_string();
+
+    bail!("{} is outdated, {}:\n\n{}", path, reason, diff.trim_end())
+}
+
+/// Compares the file with the code generated using options from its header.
+///
+/// Fails with a diff like [`check`] if they differ.
+pub async fn check_file(path: &Path, connection: &Connection) -> Result<()> {
+    let actual =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let options = Options {
+        connection: connection.clone(),
+        ..Options::from_header(&actual).context("failed to parse options")?
+    };
+
+    let code = generate(options).await?;
+    check(path, &code)
+}
+
+/// Regenerates the file in place using options from its header.
+///
+/// The file is left untouched if it's up to date.
+pub async fn regen(path: &Path, connection: &Connection) -> Result<WriteStatus> {
+    let actual =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let options = Options {
+        connection: connection.clone(),
+        ..Options::from_header(&actual).context("failed to parse options")?
+    };
+
+    let code = generate(options).await?;
+    write_code(path, &code)
+}
+
+/// Generates code for the target and writes it to the target's output.
+///
+/// The file is left untouched if it's up to date.
+pub async fn generate_target(target: &Target) -> Result<WriteStatus> {
+    let code = generate(target.options.clone()).await?;
+    write_code(&target.output, &code)
+}
+
+/// Writes the generated code to the file atomically.
+///
+/// The file is left untouched if its content is the same, so that
+/// mtimes aren't bumped and rebuilds aren't triggered.
+pub fn write_code(path: &Path, code: &str) -> Result<WriteStatus> {
+    output::write(path, code)
+}
+
+/// Generates code for the provided table, no network is required.
+///
+/// Connection options and ignored columns are not taken into account.
+pub fn generate_from_table(table: &schema::Table, options: &Options) -> Result<String> {
+    codegen::generate(table, options)
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Used by `ch2rs-macros`, not a public API.
+
+    use anyhow::Result;
+    use proc_macro2::TokenStream;
+
+    use crate::{codegen, ddl, Options};
+
+    pub fn generate_items_from_ddl(sql: &str, options: &Options) -> Result<TokenStream> {
+        let table = ddl::parse(sql, options)?;
+        codegen::generate_items(&table, options)
+    }
+}
@@ -1,30 +1,85 @@
+use std::{future::Future, ops::Range, time::Duration};
+
 use anyhow::{anyhow, bail, Context, Result};
-use clickhouse::{Client, Row};
+use clickhouse::{Client, Compression, Row};
+use heck::ToSnakeCase;
 use serde::Deserialize;
 
 use crate::{
-    options::Options,
-    schema::{Column, SqlType, Table},
+    codegen, connection, ddl,
+    options::{Connection, Options},
+    schema::{Column, DefaultKind, IgnoredColumn, SqlType, Table},
 };
 
-fn make_client(options: &Options) -> Client {
-    let url = if !options.url.starts_with("http") {
-        format!("http://{}", options.url)
+pub(crate) fn make_client(connection: &Connection) -> Result<Client> {
+    let connection = connection.resolve()?;
+    let url = connection.url.as_deref().unwrap_or_default();
+    let url = if !url.starts_with("http") {
+        format!("http://{}", url)
     } else {
-        options.url.clone()
+        url.into()
     };
 
-    let mut client = Client::default().with_url(url);
+    let mut client = connection::make_base_client(&connection)?.with_url(url);
 
-    if let Some(user) = &options.user {
+    if let Some(user) = &connection.user {
         client = client.with_user(user);
     }
 
-    if let Some(password) = &options.password {
+    if let Some(password) = &connection.password {
         client = client.with_password(password);
     }
 
+    if let Some(role) = &connection.role {
+        client = client.with_option("role", role);
+    }
+
+    if let Some(timeout) = connection.timeout {
+        client = client.with_option("max_execution_time", timeout.to_string());
+    }
+
+    if connection.no_compression {
+        client = client.with_compression(Compression::None);
+    }
+
+    for (name, value) in &connection.settings {
+        client = client.with_option(name, value);
+    }
+
+    for (name, value) in &connection.headers {
+        client = client.with_header(name, value);
+    }
+
+    Ok(client)
+}
+
+#[derive(Debug, Default, Deserialize, Row)]
+struct RawTable {
+    database: String,
+    name: String,
+    comment: String,
+    engine: String,
+    engine_full: String,
+    partition_key: String,
+    sorting_key: String,
+    primary_key: String,
+}
+
+async fn fetch_raw_table(client: &Client, database: &str, name: &str) -> Result<RawTable> {
     client
+        .query(
+            "
+            SELECT ?fields
+              FROM system.tables
+             WHERE database = ?
+               AND name = ?
+        ",
+        )
+        .bind(database)
+        .bind(name)
+        .fetch_optional::<RawTable>()
+        .await?
+        .with_context(|| format!("the table `{}.{}` doesn't exist", database, name))
 }
 
 #[derive(Debug, Deserialize, Row)]
@@ -33,9 +88,16 @@ struct RawColumn {
     #[serde(rename = "type")]
     type_: String,
     comment: String,
+    position: u64,
+    default_kind: String,
+    default_expression: String,
+    is_in_partition_key: bool,
+    is_in_sorting_key: bool,
+    is_in_primary_key: bool,
+    compression_codec: String,
 }
 
-async fn fetch_raw_columns(client: &Client, options: &Options) -> Result<Vec<RawColumn>> {
+async fn fetch_raw_columns(client: &Client, database: &str, name: &str) -> Result<Vec<RawColumn>> {
     Ok(client
         .query(
             "
@@ -43,19 +105,218 @@ async fn fetch_raw_columns(client: &Client, options: &Options) -> Result<Vec<Raw
               FROM system.columns
              WHERE database = ?
                AND table = ?
+             ORDER BY position
         ",
         )
-        .bind(&options.database)
-        .bind(&options.table)
+        .bind(database)
+        .bind(name)
         .fetch_all::<RawColumn>()
         .await?)
 }
 
-fn make_table(raw_columns: Vec<RawColumn>, options: &Options) -> Result<Table> {
+// The result of `DESCRIBE TABLE`.
+#[derive(Debug, Deserialize, Row)]
+struct RawDescribedColumn {
+    name: String,
+    #[serde(rename = "type")]
+    type_: String,
+    default_type: String,
+    default_expression: String,
+    comment: String,
+    codec_expression: String,
+    // Only to consume the column, `RowBinary` is positional.
+    #[allow(dead_code)]
+    ttl_expression: String,
+}
+
+/// Quotes the identifier with backticks, e.g. `order` becomes `` `order` ``.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+async fn describe_query(client: &Client, query: &str) -> Result<Vec<RawColumn>> {
+    let query = query.trim().trim_end_matches(';');
+    // `DESCRIBE` requires values of all parameters.
+    let query = replace_params(query, |p| {
+        // Identifiers have no default, so the parameter's name is used, e.g.
+        // `FROM {events:Identifier}` is described as `FROM events`.
+        if p.type_ == "Identifier" {
+            return quote_identifier(p.name);
+        }
+
+        let type_ = p.type_.replace('\\', "\\\\").replace('\'', "\\'");
+        format!("defaultValueOfTypeName('{}')", type_)
+    });
+
+    // `?` is a placeholder for `Query::bind()`.
+    let sql = format!("DESCRIBE TABLE ({})", query.replace('?', "??"));
+
+    let raw_columns = client
+        .query(&sql)
+        .fetch_all::<RawDescribedColumn>()
+        .await?
+        .into_iter()
+        .zip(1..)
+        .map(|(raw, position)| RawColumn {
+            name: raw.name,
+            type_: raw.type_,
+            comment: raw.comment,
+            position,
+            default_kind: raw.default_type,
+            default_expression: raw.default_expression,
+            is_in_partition_key: false,
+            is_in_sorting_key: false,
+            is_in_primary_key: false,
+            compression_codec: raw.codec_expression,
+        })
+        .collect();
+
+    Ok(raw_columns)
+}
+
+/// A query parameter, e.g. `{from:DateTime}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Param<'a> {
+    name: &'a str,
+    type_: &'a str,
+    range: Range<usize>,
+}
+
+/// Returns parameters of the query in order of appearance.
+fn find_params(query: &str) -> Vec<Param<'_>> {
+    let bytes = query.as_bytes();
+    let mut params = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        match bytes[pos] {
+            // Skip literals and quoted identifiers.
+            quote @ (b'\'' | b'"' | b'`') => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != quote {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                pos += 1;
+            }
+            b'{' => match parse_param(query, pos) {
+                Some(param) => {
+                    pos = param.range.end;
+                    params.push(param);
+                }
+                None => pos += 1,
+            },
+            _ => pos += 1,
+        }
+    }
+
+    params
+}
+
+// {name:Type}
+fn parse_param(query: &str, start: usize) -> Option<Param<'_>> {
+    let (name, rest) = query[start + 1..].split_once(':')?;
+    let is_ident = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !is_ident(name.trim()) {
+        return None;
+    }
+
+    // The type can contain quotes, e.g. `{ts:DateTime('UTC')}`.
+    let mut quoted = false;
+    for (idx, c) in rest.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '}' if !quoted => {
+                return Some(Param {
+                    name: name.trim(),
+                    type_: rest[..idx].trim(),
+                    range: start..start + 1 + name.len() + 1 + idx + 1,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Returns parameters of the query as columns, every parameter is returned once.
+pub(crate) fn params(query: &str) -> Result<Vec<Column>> {
+    let mut params: Vec<Column> = Vec::new();
+
+    for param in find_params(query) {
+        // Identifiers are passed as strings, e.g. `{table:Identifier}`.
+        let mut type_ = if param.type_ == "Identifier" {
+            SqlType::String
+        } else {
+            parse_type(param.type_)
+                .with_context(|| format!("failed to parse the `{}` type", param.type_))?
+        };
+
+        // Enum parameters are passed by names.
+        if let SqlType::Enum8(_) | SqlType::Enum16(_) = type_ {
+            type_ = SqlType::String;
+        }
+
+        // Names become fields of `Params`.
+        let field = param.name.to_snake_case();
+        if let Some(other) = params
+            .iter()
+            .find(|p| p.name != param.name && p.name.to_snake_case() == field)
+        {
+            bail!(
+                "the `{}` and `{}` parameters are both named `{}` in Rust",
+                other.name,
+                param.name,
+                field
+            );
+        }
+
+        match params.iter().find(|p| p.name == param.name) {
+            Some(prev) if prev.type_ != type_ => bail!(
+                "the `{}` parameter is used with different types: `{}` and `{}`",
+                param.name,
+                prev.type_,
+                type_
+            ),
+            Some(_) => {}
+            None => params.push(Column::new(param.name, type_)),
+        }
+    }
+
+    Ok(params)
+}
+
+fn replace_params(query: &str, f: impl Fn(&Param<'_>) -> String) -> String {
+    let mut result = String::with_capacity(query.len());
+    let mut pos = 0;
+
+    for param in find_params(query) {
+        result.push_str(&query[pos..param.range.start]);
+        result.push_str(&f(&param));
+        pos = param.range.end;
+    }
+
+    result.push_str(&query[pos..]);
+    result
+}
+
+fn make_table(
+    raw_table: RawTable,
+    raw_columns: Vec<RawColumn>,
+    options: &Options,
+) -> Result<Table> {
     let mut columns = Vec::new();
+    let mut ignored = Vec::new();
 
     for raw_column in raw_columns {
         if options.ignore.contains(&raw_column.name) {
+            // The type isn't required to be supported, but it's normalized if possible.
+            let type_ = parse_type(&raw_column.type_).map_or(raw_column.type_, |t| t.to_string());
+            ignored.push(IgnoredColumn {
+                name: raw_column.name,
+                type_,
+                position: raw_column.position,
+            });
             continue;
         }
 
@@ -64,25 +325,75 @@ fn make_table(raw_columns: Vec<RawColumn>, options: &Options) -> Result<Table> {
         columns.push(column);
     }
 
-    Ok(Table { columns })
+    // `Row` is (de)serialized positionally, so the order must match the server.
+    columns.sort_by_key(|c| c.position);
+
+    Ok(Table {
+        ttl: extract_ttl(&raw_table.engine_full).into(),
+        database: raw_table.database,
+        name: raw_table.name,
+        comment: raw_table.comment,
+        engine: raw_table.engine,
+        engine_full: raw_table.engine_full,
+        partition_key: raw_table.partition_key,
+        sorting_key: raw_table.sorting_key,
+        primary_key: raw_table.primary_key,
+        columns,
+        ignored,
+    })
+}
+
+// `system.tables` has no separate column for TTL, but `engine_full` contains it:
+// MergeTree ORDER BY u8 TTL d + toIntervalDay(1) SETTINGS index_granularity = 8192
+fn extract_ttl(engine_full: &str) -> &str {
+    let Some((_, rest)) = engine_full.split_once(" TTL ") else {
+        return "";
+    };
+
+    rest.split_once(" SETTINGS ")
+        .map_or(rest, |(ttl, _)| ttl)
+        .trim()
 }
 
 fn make_column(raw: RawColumn) -> Result<Column> {
     let type_ = parse_type(&raw.type_)
         .with_context(|| format!("failed to parse the `{}` type", raw.type_))?;
 
+    let default_kind = parse_default_kind(&raw.default_kind)?;
+
     Ok(Column {
         name: raw.name,
         type_,
         comment: raw.comment,
+        position: raw.position,
+        default_kind,
+        default_expression: raw.default_expression,
+        is_in_partition_key: raw.is_in_partition_key,
+        is_in_sorting_key: raw.is_in_sorting_key,
+        is_in_primary_key: raw.is_in_primary_key,
+        compression_codec: raw.compression_codec,
     })
 }
 
+fn parse_default_kind(raw: &str) -> Result<Option<DefaultKind>> {
+    Ok(Some(match raw {
+        "" => return Ok(None),
+        "DEFAULT" => DefaultKind::Default,
+        "MATERIALIZED" => DefaultKind::Materialized,
+        "ALIAS" => DefaultKind::Alias,
+        "EPHEMERAL" => DefaultKind::Ephemeral,
+        _ => bail!("unknown default kind `{}`", raw),
+    }))
+}
+
+/// Parses a type in the way ClickHouse formats it, e.g. `Array(LowCardinality(String))`.
+///
+/// SQL aliases, e.g. `BIGINT UNSIGNED` or `VARCHAR(255)`, are also accepted.
 pub fn parse_type(raw: &str) -> Result<SqlType> {
     let raw = raw.trim();
 
     let raw = if let Some(args) = extract_inner(raw, "SimpleAggregateFunction") {
-        let mut tokens = args.split(", ").skip(1);
+        let mut tokens = split_args(args).into_iter().skip(1);
         let type1 = tokens
             .next()
             .ok_or_else(|| anyhow!("single-arg SimpleAggregateFunction"))?;
@@ -143,35 +454,50 @@ pub fn parse_type(raw: &str) -> Result<SqlType> {
                 SqlType::Enum16(parse_kv_list(inner).context("invalid enum")?)
             }
             // Decimal(prec, scale)
+            // Decimal(prec)
             else if let Some(inner) = extract_inner(raw, "Decimal") {
-                let (prec, scale) = inner.split_once(',').context("invalid decimal")?;
-                let prec = prec.trim().parse().context("invalid prec")?;
-                let scale = scale.trim().parse().context("invalid precision")?;
-                SqlType::Decimal(prec, scale)
+                parse_decimal(inner)?
+            }
+            // Decimal32(scale), Decimal64(scale), ...
+            else if let Some((prec, inner)) = [
+                (9, "Decimal32"),
+                (18, "Decimal64"),
+                (38, "Decimal128"),
+                (76, "Decimal256"),
+            ]
+            .into_iter()
+            .find_map(|(prec, name)| extract_inner(raw, name).map(|inner| (prec, inner)))
+            {
+                SqlType::Decimal(prec, inner.trim().parse().context("invalid scale")?)
             }
             // FixedString(size)
             else if let Some(inner) = extract_inner(raw, "FixedString") {
-                SqlType::FixedString(inner.parse().context("invalid size")?)
+                SqlType::FixedString(inner.trim().parse().context("invalid size")?)
             }
             // Array(type)
             else if let Some(inner) = extract_inner(raw, "Array") {
                 SqlType::Array(Box::new(parse_type(inner)?))
             }
             // Tuple(a, b)
+            // Tuple(name_a a, name_b b)
             else if let Some(inner) = extract_inner(raw, "Tuple") {
                 SqlType::Tuple(
-                    inner
-                        .split(',')
-                        .map(parse_type)
+                    split_args(inner)
+                        .into_iter()
+                        .map(parse_tuple_element)
                         .collect::<Result<Vec<_>>>()?,
                 )
             }
             // Map(key, value)
             else if let Some(inner) = extract_inner(raw, "Map") {
-                let (key, value) = inner.split_once(',').context("invalid map")?;
+                let [key, value] = split_args(inner)[..] else {
+                    bail!("invalid map");
+                };
                 let key = parse_type(key).context("invalid key")?;
                 let value = parse_type(value).context("invalid value")?;
                 SqlType::Map(Box::new(key), Box::new(value))
+            } else if let Some(type_) = parse_alias(raw)? {
+                type_
             } else {
                 bail!("unknown type");
             }
@@ -180,32 +506,462 @@ pub fn parse_type(raw: &str) -> Result<SqlType> {
 }
 
 fn extract_inner<'a>(raw: &'a str, wrapper: &str) -> Option<&'a str> {
-    if raw.starts_with(wrapper) && raw[wrapper.len()..].starts_with('(') {
+    if raw.starts_with(wrapper) && raw[wrapper.len()..].starts_with('(') && raw.ends_with(')') {
         Some(&raw[wrapper.len() + 1..raw.len() - 1])
     } else {
         None
     }
 }
 
+// Splits `a, Map(b, c), 'd, e'` by commas on the top level.
+fn split_args(raw: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (idx, c) in raw.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                args.push(raw[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+
+    args.push(raw[start..].trim());
+    args
+}
+
+fn parse_tuple_element(raw: &str) -> Result<SqlType> {
+    parse_type(raw).or_else(|err| {
+        // Named elements: `name Type`.
+        match raw.split_once(' ') {
+            Some((name, type_)) if !name.contains('(') => parse_type(type_),
+            _ => Err(err),
+        }
+    })
+}
+
+fn parse_decimal(raw: &str) -> Result<SqlType> {
+    let (prec, scale) = match split_args(raw)[..] {
+        [prec] => (prec, "0"),
+        [prec, scale] => (prec, scale),
+        _ => bail!("invalid decimal"),
+    };
+
+    let prec = prec.parse().context("invalid precision")?;
+    let scale = scale.parse().context("invalid scale")?;
+    Ok(SqlType::Decimal(prec, scale))
+}
+
+// Case-insensitive aliases, see `system.data_type_families`.
+fn parse_alias(raw: &str) -> Result<Option<SqlType>> {
+    let (name, args) = match raw.split_once('(') {
+        Some((name, args)) => (name, args.strip_suffix(')')),
+        None => (raw, None),
+    };
+
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    Ok(Some(match name.to_uppercase().as_str() {
+        "TINYINT" | "TINYINT SIGNED" | "INT1" | "INT1 SIGNED" | "BYTE" => SqlType::Int8,
+        "SMALLINT" | "SMALLINT SIGNED" => SqlType::Int16,
+        "INT" | "INT SIGNED" | "INTEGER" | "INTEGER SIGNED" | "MEDIUMINT" | "MEDIUMINT SIGNED" => {
+            SqlType::Int32
+        }
+        "BIGINT" | "BIGINT SIGNED" | "SIGNED" | "TIME" => SqlType::Int64,
+        "TINYINT UNSIGNED" | "INT1 UNSIGNED" => SqlType::UInt8,
+        "SMALLINT UNSIGNED" | "YEAR" => SqlType::UInt16,
+        "INT UNSIGNED" | "INTEGER UNSIGNED" | "MEDIUMINT UNSIGNED" => SqlType::UInt32,
+        "BIGINT UNSIGNED" | "UNSIGNED" | "BIT" | "SET" => SqlType::UInt64,
+        "BOOL" | "BOOLEAN" => SqlType::Bool,
+        "FLOAT" | "REAL" | "SINGLE" => SqlType::Float32,
+        "DOUBLE" | "DOUBLE PRECISION" => SqlType::Float64,
+        "TEXT"
+        | "TINYTEXT"
+        | "MEDIUMTEXT"
+        | "LONGTEXT"
+        | "BLOB"
+        | "TINYBLOB"
+        | "MEDIUMBLOB"
+        | "LONGBLOB"
+        | "BYTEA"
+        | "VARCHAR"
+        | "VARCHAR2"
+        | "NVARCHAR"
+        | "CHAR"
+        | "NCHAR"
+        | "CHARACTER"
+        | "VARBINARY"
+        | "CHAR VARYING"
+        | "CHARACTER VARYING"
+        | "NCHAR VARYING"
+        | "NATIONAL CHAR"
+        | "NATIONAL CHARACTER"
+        | "NATIONAL CHAR VARYING"
+        | "NATIONAL CHARACTER VARYING"
+        | "BINARY VARYING"
+        | "CHAR LARGE OBJECT"
+        | "CHARACTER LARGE OBJECT"
+        | "NCHAR LARGE OBJECT"
+        | "NATIONAL CHARACTER LARGE OBJECT"
+        | "BINARY LARGE OBJECT" => SqlType::String,
+        "BINARY" => match args {
+            Some(size) => SqlType::FixedString(size.trim().parse().context("invalid size")?),
+            None => SqlType::FixedString(1),
+        },
+        "TIMESTAMP" => SqlType::DateTime(None),
+        "DECIMAL" | "DEC" | "NUMERIC" | "FIXED" => match args {
+            Some(args) => parse_decimal(args)?,
+            None => SqlType::Decimal(10, 0),
+        },
+        "INET4" => SqlType::IPv4,
+        "INET6" => SqlType::IPv6,
+        _ => return Ok(None),
+    }))
+}
+
 // 'K' = v, 'K2' = v2
 fn parse_kv_list(raw: &str) -> Result<Vec<(String, i32)>> {
-    raw.split(", ")
+    split_args(raw)
+        .into_iter()
         .map(|pair| {
             let (k, v) = pair
-                .split_once(" = ")
+                .rsplit_once('=')
                 .with_context(|| format!("invalid key-value pair `{}`", pair))?;
-            let k = k.get(1..k.len() - 1).context("invalid variant key")?;
-            let v = v.parse().context("invalid variant value")?;
-            Ok((k.into(), v))
+            let k = k.trim();
+            let k = k
+                .strip_prefix('\'')
+                .and_then(|k| k.strip_suffix('\''))
+                .context("invalid variant key")?;
+            let v = v.trim().parse().context("invalid variant value")?;
+            Ok((unescape(k), v))
         })
         .collect()
 }
 
-pub async fn mine(options: &Options) -> Result<Table> {
-    let client = make_client(options);
-    let raw_columns = fetch_raw_columns(&client, options)
+fn unescape(raw: &str) -> String {
+    let mut result = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Describes the query's result, the table is unnamed.
+pub async fn mine_query(client: &Client, options: &Options, query: &str) -> Result<Table> {
+    // Fail before querying the server.
+    let params = params(query).context("failed to parse parameters")?;
+    codegen::check_params(&params, options)?;
+
+    let raw_columns = describe_query(client, query)
+        .await
+        .context("failed to describe the query")?;
+    let table = make_table(RawTable::default(), raw_columns, options)
+        .context("failed to make the table")?;
+    Ok(table)
+}
+
+async fn fetch_table(
+    client: &Client,
+    database: &str,
+    name: &str,
+    options: &Options,
+) -> Result<Table> {
+    let raw_table = fetch_raw_table(client, database, name)
+        .await
+        .context("failed to fetch the table")?;
+    let raw_columns = fetch_raw_columns(client, database, name)
         .await
         .context("failed to fetch columns")?;
-    let table = make_table(raw_columns, options).context("failed to make the table")?;
+    make_table(raw_table, raw_columns, options).context("failed to make the table")
+}
+
+// Distributed(cluster, database, table[, sharding_key[, policy_name]])
+fn parse_distributed(table: &Table) -> Result<(String, String)> {
+    let args = table
+        .engine_full
+        .strip_prefix("Distributed(")
+        .and_then(|rest| {
+            let mut depth = 0usize;
+            let mut quoted = false;
+            rest.char_indices().find_map(|(idx, c)| {
+                match c {
+                    '\'' => quoted = !quoted,
+                    '(' if !quoted => depth += 1,
+                    ')' if !quoted && depth == 0 => return Some(&rest[..idx]),
+                    ')' if !quoted => depth -= 1,
+                    _ => {}
+                }
+                None
+            })
+        })
+        .context("invalid engine")?;
+
+    let unquote = |arg: &str| {
+        let arg = arg.trim();
+        arg.strip_prefix(['\'', '`'])
+            .and_then(|a| a.strip_suffix(['\'', '`']))
+            .map_or_else(|| arg.to_string(), unescape)
+    };
+
+    let args = split_args(args);
+    let (Some(database), Some(name)) = (args.get(1), args.get(2)) else {
+        bail!("invalid engine");
+    };
+
+    let database = match unquote(database) {
+        database if database.is_empty() || database == "currentDatabase()" => {
+            table.database.clone()
+        }
+        database => database,
+    };
+
+    Ok((database, unquote(name)))
+}
+
+// Rows are read through the Distributed table, so its schema is used, but it
+// must agree with the local table's one.
+async fn verify_distributed(client: &Client, table: &Table, options: &Options) -> Result<()> {
+    let (database, name) = parse_distributed(table)?;
+    let local = fetch_table(client, &database, &name, options)
+        .await
+        .with_context(|| format!("failed to fetch the local table `{}.{}`", database, name))?;
+
+    let mismatches = table
+        .columns
+        .iter()
+        .filter_map(
+            |column| match local.columns.iter().find(|c| c.name == column.name) {
+                None => Some(format!("`{}` is missing", column.name)),
+                Some(c) if c.type_ != column.type_ => Some(format!(
+                    "`{}` is `{}` instead of `{}`",
+                    column.name, c.type_, column.type_
+                )),
+                Some(_) => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if !mismatches.is_empty() {
+        bail!(
+            "the local table `{}.{}` doesn't match: {}",
+            database,
+            name,
+            mismatches.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn mine(client: &Client, options: &Options) -> Result<Table> {
+    let table = fetch_table(client, &options.database, &options.table, options).await?;
+
+    if table.engine == "Distributed" {
+        verify_distributed(client, &table, options)
+            .await
+            .context("failed to verify the Distributed table")?;
+    }
+
     Ok(table)
 }
+
+/// Fails if mining takes longer than `--timeout`, responses included.
+pub(crate) async fn with_timeout<T>(
+    connection: &Connection,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(timeout) = connection.timeout else {
+        return future.await;
+    };
+
+    tokio::time::timeout(Duration::from_secs(timeout), future)
+        .await
+        .map_err(|_| anyhow!("timed out after {} seconds", timeout))?
+}
+
+/// A dictionary with its key columns and all columns.
+pub struct Dictionary {
+    pub key: Table,
+    pub row: Table,
+}
+
+#[derive(Debug, Deserialize, Row)]
+struct RawDictionary {
+    database: String,
+    name: String,
+    comment: String,
+    layout: String,
+    key_names: Vec<String>,
+    key_types: Vec<String>,
+    attribute_names: Vec<String>,
+    attribute_types: Vec<String>,
+}
+
+pub async fn mine_dictionary(client: &Client, options: &Options) -> Result<Dictionary> {
+    let (database, name) = (&options.database, &options.table);
+
+    let raw = client
+        .query(
+            "
+            SELECT database, name, comment, type,
+                   `key.names`, `key.types`, `attribute.names`, `attribute.types`
+              FROM system.dictionaries
+             WHERE database = ?
+               AND name = ?
+        ",
+        )
+        .bind(database)
+        .bind(name)
+        .fetch_optional::<RawDictionary>()
+        .await
+        .context("failed to fetch the dictionary")?
+        .with_context(|| format!("the dictionary `{}.{}` doesn't exist", database, name))?;
+
+    if raw.key_names.is_empty() {
+        bail!(
+            "the dictionary `{}.{}` has no structure, probably it isn't loaded yet",
+            database,
+            name
+        );
+    }
+
+    let keys = raw.key_names.iter().zip(&raw.key_types);
+    let attributes = raw.attribute_names.iter().zip(&raw.attribute_types);
+
+    let make_columns = |columns: Vec<(&String, &String)>| {
+        let key_count = raw.key_names.len();
+        columns
+            .into_iter()
+            .zip(1..)
+            .map(|((name, type_), position)| RawColumn {
+                name: name.clone(),
+                type_: type_.clone(),
+                comment: String::new(),
+                position,
+                default_kind: String::new(),
+                default_expression: String::new(),
+                is_in_partition_key: false,
+                is_in_sorting_key: false,
+                is_in_primary_key: position as usize <= key_count,
+                compression_codec: String::new(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let make_raw_table = || RawTable {
+        database: raw.database.clone(),
+        name: raw.name.clone(),
+        comment: raw.comment.clone(),
+        engine: "Dictionary".into(),
+        engine_full: format!("Dictionary({})", raw.layout),
+        ..RawTable::default()
+    };
+
+    let key = make_table(
+        make_raw_table(),
+        make_columns(keys.clone().collect()),
+        options,
+    )
+    .context("failed to make the key")?;
+    let row = make_table(
+        make_raw_table(),
+        make_columns(keys.chain(attributes).collect()),
+        options,
+    )
+    .context("failed to make the row")?;
+
+    Ok(Dictionary { key, row })
+}
+
+/// A materialized view with rows of its query and its target table.
+pub struct MaterializedView {
+    pub query: String,
+    pub source: Table,
+    pub target: Table,
+}
+
+#[derive(Debug, Deserialize, Row)]
+struct RawView {
+    engine: String,
+    as_select: String,
+    create_table_query: String,
+    uuid: String,
+}
+
+pub async fn mine_mv(client: &Client, options: &Options) -> Result<MaterializedView> {
+    const NIL_UUID: &str = "00000000-0000-0000-0000-000000000000";
+
+    let (database, name) = (&options.database, &options.table);
+
+    let raw_view = client
+        .query(
+            "
+            SELECT engine, as_select, create_table_query, toString(uuid)
+              FROM system.tables
+             WHERE database = ?
+               AND name = ?
+        ",
+        )
+        .bind(database)
+        .bind(name)
+        .fetch_optional::<RawView>()
+        .await
+        .context("failed to fetch the view")?
+        .with_context(|| format!("the table `{}.{}` doesn't exist", database, name))?;
+
+    if raw_view.engine != "MaterializedView" {
+        bail!(
+            "the table `{}.{}` is not a materialized view",
+            database,
+            name
+        );
+    }
+
+    // Without `TO` the data is stored in an implicit inner table.
+    let (target_database, target_name) = match ddl::parse_mv_target(&raw_view.create_table_query) {
+        Some((target_database, target_name)) => (
+            target_database.unwrap_or_else(|| database.clone()),
+            target_name,
+        ),
+        None if raw_view.uuid != NIL_UUID => {
+            (database.clone(), format!(".inner_id.{}", raw_view.uuid))
+        }
+        None => (database.clone(), format!(".inner.{}", name)),
+    };
+
+    let source = describe_query(client, &raw_view.as_select)
+        .await
+        .and_then(|raw_columns| make_table(RawTable::default(), raw_columns, options))
+        .context("failed to describe the view's query")?;
+
+    let target = fetch_table(client, &target_database, &target_name, options)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to fetch the target table `{}.{}`",
+                target_database, target_name
+            )
+        })?;
+
+    Ok(MaterializedView {
+        query: raw_view.as_select,
+        source,
+        target,
+    })
+}
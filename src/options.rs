@@ -1,25 +1,68 @@
-use std::fmt::Write;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Deserializer};
 use structopt::StructOpt;
 
 use crate::schema::SqlType;
 
-#[derive(Debug, StructOpt)]
+const AFTER_HELP: &str = "\
+Missing connection options are taken from CLICKHOUSE_URL, CLICKHOUSE_USER and \
+CLICKHOUSE_PASSWORD environment variables, then from ~/.clickhouse-client/config.xml.
+
+Use `ch2rs dump --help` to write schemas to a snapshot file, \
+`ch2rs regen --help` to regenerate files and `ch2rs diff --help` to compare schemas.
+
+Without arguments tables configured in ch2rs.toml are generated, \
+add `--watch` to regenerate them when their schemas change.";
+
+/// Options of generation, usually parsed from command line arguments.
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(after_help = AFTER_HELP)]
 pub struct Options {
-    /// ClickHouse server's URL.
-    #[structopt(short = "U", default_value = "localhost:8123")]
-    pub url: String,
-    #[structopt(short = "u")]
-    pub user: Option<String>,
-    #[structopt(short = "p")]
-    pub password: Option<String>,
+    #[structopt(flatten)]
+    pub connection: Connection,
 
     /// A database where the table is placed in.
-    #[structopt(short = "d", default_value = "default")]
+    #[structopt(short = "d", env = "CLICKHOUSE_DATABASE", default_value = "default")]
     pub database: String,
-    /// The table's name.
+    /// The table's name, not required with `--query` and `--query-file`.
+    #[structopt(default_value = "", hide_default_value = true)]
     pub table: String,
+    /// Read `CREATE TABLE` statements from the file instead of the server,
+    /// `-` means stdin.
+    #[structopt(long, parse(from_os_str))]
+    pub ddl: Option<PathBuf>,
+    /// Read the table from a snapshot written by `ch2rs dump` instead of the server.
+    #[structopt(long, parse(from_os_str), conflicts_with = "ddl")]
+    pub schema_file: Option<PathBuf>,
+    /// Generate a row for the query's result instead of the table,
+    /// parameters like `{from:DateTime}` are allowed.
+    #[structopt(long, conflicts_with_all = &["ddl", "schema-file", "query-file"])]
+    pub query: Option<String>,
+    /// Read the query from the file, or queries from `.sql` files in the directory.
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["ddl", "schema-file"])]
+    pub query_file: Option<PathBuf>,
+    /// Generate rows of the materialized view's query and its target table
+    /// in the `source` and `target` modules.
+    #[structopt(long, conflicts_with_all = &["ddl", "schema-file", "query", "query-file"])]
+    pub follow_mv: bool,
+    /// The table is a dictionary, generate the `Key` struct along with the row.
+    #[structopt(long, conflicts_with_all = &["ddl", "schema-file", "query", "query-file", "follow-mv"])]
+    pub dictionary: bool,
+    /// Generate a row readable in both versions of the table during migrations,
+    /// the source is `server[:<url>]`, `ddl:<path>` or `snapshot:<path>`.
+    #[structopt(long, conflicts_with_all = &["query", "query-file", "follow-mv", "dictionary"])]
+    pub compatible_with: Option<SchemaSource>,
+    /// The name of the generated struct instead of `Row`.
+    #[structopt(long)]
+    pub struct_name: Option<String>,
 
     /// Generate `Serialize` instances.
     #[structopt(short = "S")]
@@ -30,6 +73,15 @@ pub struct Options {
     /// Generate only owned types.
     #[structopt(long)]
     pub owned: bool,
+    /// Represent `String` columns as `&str`, `String` or `Cow<str>`.
+    #[structopt(long, default_value = "str", possible_values = &["str", "string", "cow"])]
+    pub string_repr: StringRepr,
+    /// Use a borrowed type for the provided column even with `--owned`.
+    #[structopt(long, number_of_values = 1)]
+    pub borrow: Vec<String>,
+    /// Use an owned type for the provided column.
+    #[structopt(long, number_of_values = 1)]
+    pub own: Vec<String>,
     /// Override the type,
     /// e.g. 'Decimal(18, 9)=fixnum::FixedPoint<i64, typenum::U9>'
     #[structopt(short = "T", parse(try_from_str = parse_type), number_of_values = 1)]
@@ -43,12 +95,280 @@ pub struct Options {
     /// Ignore a specified column.
     #[structopt(short = "I", number_of_values = 1)]
     pub ignore: Vec<String>,
+    /// Generate `verify_schema()` comparing columns with the server at runtime.
+    #[structopt(long)]
+    pub verify_schema: bool,
+    /// Generate `insert()` and `select()` using the table's name.
+    #[structopt(long)]
+    pub query_helpers: bool,
+    /// Generate `inserter()` along with query helpers, it requires the
+    /// `inserter` feature of `clickhouse`.
+    #[structopt(long, requires = "query-helpers")]
+    pub inserter: bool,
     /// Add `#[derive(<trait>)]` to the generated types.
     #[structopt(long = "derive", number_of_values = 1, name = "trait")]
     pub derives: Vec<String>,
+    /// Compare the generated code with the file instead of printing it,
+    /// fail with a diff if they differ. Without the table, options are
+    /// taken from the file's header.
+    #[structopt(long)]
+    pub check: Option<PathBuf>,
+    /// Write the code to the file instead of stdout, it's left untouched if
+    /// nothing has changed.
+    #[structopt(short = "o", long, parse(from_os_str), conflicts_with = "check")]
+    pub output: Option<PathBuf>,
+    /// Write the code to `<dir>/<table>.rs`.
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["output", "check"])]
+    pub out_dir: Option<PathBuf>,
+    /// Regenerate the output when the schema changes, without the table
+    /// every table of `ch2rs.toml` is watched.
+    #[structopt(long, conflicts_with = "check")]
+    pub watch: bool,
+    /// How often to poll the schema with `--watch`, in seconds.
+    #[structopt(long, default_value = "2", parse(try_from_str = parse_interval))]
+    pub interval: u64,
+}
+
+// Options of connection to the server.
+// Not a doc comment, otherwise structopt uses it as `about` of every command.
+#[derive(Clone, Default, StructOpt, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Connection {
+    /// ClickHouse server's URL, `localhost:8123` by default.
+    #[structopt(short = "U")]
+    pub url: Option<String>,
+    #[structopt(short = "u")]
+    pub user: Option<String>,
+    /// Avoid it, the password leaks into the shell's history.
+    #[structopt(short = "p")]
+    pub password: Option<String>,
+    /// Ask for the password interactively.
+    #[structopt(long, conflicts_with = "password")]
+    #[serde(skip)]
+    pub ask_password: bool,
+    /// Pass the setting to the server, e.g. `flatten_nested=0`.
+    #[structopt(long = "setting", parse(try_from_str = parse_setting), number_of_values = 1)]
+    #[serde(deserialize_with = "deserialize_pairs")]
+    pub settings: Vec<(String, String)>,
+    /// Add the HTTP header to requests, e.g. `X-Team: analytics`.
+    #[structopt(long = "header", parse(try_from_str = parse_header), number_of_values = 1)]
+    #[serde(deserialize_with = "deserialize_pairs")]
+    pub headers: Vec<(String, String)>,
+    /// Timeout of connecting to the server and fetching every schema, in
+    /// seconds. It's also passed as `max_execution_time`, so the server
+    /// cancels slower queries.
+    #[structopt(long)]
+    pub timeout: Option<u64>,
+    /// Disable compression of responses.
+    #[structopt(long)]
+    pub no_compression: bool,
+    /// Execute queries with the role.
+    #[structopt(long)]
+    pub role: Option<String>,
+    /// Verify the server with CA certificates from the PEM file.
+    #[structopt(long, parse(from_os_str))]
+    pub ca_cert: Option<PathBuf>,
+    /// Authenticate with the client certificate from the PEM file (mTLS).
+    #[structopt(long, parse(from_os_str), requires = "client-key")]
+    pub client_cert: Option<PathBuf>,
+    /// The private key of `--client-cert` in the PEM file.
+    #[structopt(long, parse(from_os_str), requires = "client-cert")]
+    pub client_key: Option<PathBuf>,
+}
+
+// The password is redacted, options are printed e.g. by `Builder`.
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection")
+            .field("url", &self.url)
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("ask_password", &self.ask_password)
+            .field("settings", &self.settings)
+            .field("headers", &self.headers)
+            .field("timeout", &self.timeout)
+            .field("no_compression", &self.no_compression)
+            .field("role", &self.role)
+            .field("ca_cert", &self.ca_cert)
+            .field("client_cert", &self.client_cert)
+            .field("client_key", &self.client_key)
+            .finish()
+    }
+}
+
+fn parse_setting(s: &str) -> Result<(String, String)> {
+    let (name, value) = s.split_once('=').context("expected `name=value`")?;
+    Ok((name.trim().into(), value.trim().into()))
+}
+
+fn parse_header(s: &str) -> Result<(String, String)> {
+    let (name, value) = s.split_once(':').context("expected `Name: value`")?;
+    Ok((name.trim().into(), value.trim().into()))
+}
+
+// Settings and headers are tables in configs, e.g. `settings = { flatten_nested = 0 }`.
+fn deserialize_pairs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, String)>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        String(String),
+        Integer(i64),
+        Bool(bool),
+    }
+
+    let map = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(value) => value,
+                Value::Integer(value) => value.to_string(),
+                Value::Bool(value) => (value as u8).to_string(),
+            };
+            (name, value)
+        })
+        .collect())
+}
+
+/// Options of `ch2rs dump`.
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(
+    name = "ch2rs dump",
+    about = "Writes schemas of tables to a snapshot file"
+)]
+pub struct DumpOptions {
+    #[structopt(flatten)]
+    pub connection: Connection,
+    /// A database where tables without the `db.` prefix are placed in.
+    #[structopt(short = "d", env = "CLICKHOUSE_DATABASE", default_value = "default")]
+    pub database: String,
+    /// A file to write the snapshot to, stdout by default.
+    #[structopt(short = "o", long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// Tables to dump, e.g. `events` or `analytics.events`.
+    #[structopt(required = true)]
+    pub tables: Vec<String>,
+}
+
+/// Options of `ch2rs regen`.
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(
+    name = "ch2rs regen",
+    about = "Regenerates files using options from their headers"
+)]
+pub struct RegenOptions {
+    #[structopt(flatten)]
+    pub connection: Connection,
+    /// Files generated by ch2rs, rewritten in place.
+    #[structopt(required = true, parse(from_os_str))]
+    pub files: Vec<PathBuf>,
+}
+
+/// Options of `ch2rs diff`.
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(
+    name = "ch2rs diff",
+    about = "Compares the table's schema in two sources",
+    after_help = DIFF_AFTER_HELP
+)]
+pub struct DiffOptions {
+    #[structopt(flatten)]
+    pub connection: Connection,
+    /// A database where the table is placed in.
+    #[structopt(short = "d", env = "CLICKHOUSE_DATABASE", default_value = "default")]
+    pub database: String,
+    /// Ignore a specified column.
+    #[structopt(short = "I", number_of_values = 1)]
+    pub ignore: Vec<String>,
+    pub table: String,
+    /// The source the existing struct is generated from.
+    pub old: SchemaSource,
+    /// The source to compare with.
+    pub new: SchemaSource,
+}
+
+const DIFF_AFTER_HELP: &str = "\
+Sources are `server` for the server of connection options, `server:<url>` for another server \
+with the same credentials, `ddl:<path>` for `CREATE TABLE` statements and `snapshot:<path>` \
+for a file written by `ch2rs dump`.
+
+Every change is classified by its effect on the struct generated from the old source, \
+the command fails if any of them breaks deserialization or insertion.";
+
+/// Where to load the table's schema from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaSource {
+    /// The server, `None` means the one of connection options.
+    Server(Option<String>),
+    Ddl(PathBuf),
+    Snapshot(PathBuf),
+}
+
+impl FromStr for SchemaSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+
+        Ok(match (kind, arg) {
+            ("server", None) => SchemaSource::Server(None),
+            ("server", Some(url)) if !url.is_empty() => SchemaSource::Server(Some(url.into())),
+            ("ddl", Some(path)) if !path.is_empty() => SchemaSource::Ddl(path.into()),
+            ("snapshot", Some(path)) if !path.is_empty() => SchemaSource::Snapshot(path.into()),
+            _ => bail!("expected `server`, `server:<url>`, `ddl:<path>` or `snapshot:<path>`"),
+        })
+    }
+}
+
+impl fmt::Display for SchemaSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaSource::Server(None) => f.write_str("server"),
+            SchemaSource::Server(Some(url)) => write!(f, "server:{}", url),
+            SchemaSource::Ddl(path) => write!(f, "ddl:{}", path.display()),
+            SchemaSource::Snapshot(path) => write!(f, "snapshot:{}", path.display()),
+        }
+    }
+}
+
+/// A representation of `String` columns.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StringRepr {
+    Str,
+    String,
+    Cow,
+}
+
+impl FromStr for StringRepr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "str" => StringRepr::Str,
+            "string" => StringRepr::String,
+            "cow" => StringRepr::Cow,
+            _ => bail!("unknown string representation"),
+        })
+    }
+}
+
+impl fmt::Display for StringRepr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StringRepr::Str => "str",
+            StringRepr::String => "string",
+            StringRepr::Cow => "cow",
+        })
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// A type override by SQL type, `-T`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Type {
     pub sql: SqlType,
     pub type_: String,
@@ -62,7 +382,8 @@ fn parse_type(s: &str) -> Result<Type> {
     })
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// A type override by column's name, `-O`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct Override {
     pub column: String,
     pub type_: String,
@@ -76,16 +397,130 @@ fn parse_override(s: &str) -> Result<Override> {
     })
 }
 
+// Zero would make `--watch` poll the server in a busy loop.
+fn parse_interval(s: &str) -> Result<u64> {
+    match s.parse()? {
+        0 => bail!("the interval must be at least 1 second"),
+        secs => Ok(secs),
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            connection: Connection::default(),
+            database: "default".into(),
+            table: String::new(),
+            ddl: None,
+            schema_file: None,
+            query: None,
+            query_file: None,
+            follow_mv: false,
+            dictionary: false,
+            compatible_with: None,
+            check: None,
+            output: None,
+            out_dir: None,
+            watch: false,
+            interval: 2,
+            struct_name: None,
+            serialize: false,
+            deserialize: false,
+            owned: false,
+            string_repr: StringRepr::Str,
+            borrow: Vec::new(),
+            own: Vec::new(),
+            types: Vec::new(),
+            overrides: Vec::new(),
+            bytes: Vec::new(),
+            ignore: Vec::new(),
+            verify_schema: false,
+            query_helpers: false,
+            inserter: false,
+            derives: Vec::new(),
+        }
+    }
+}
+
 impl Options {
+    /// Returns the representation of the provided `String` column.
+    pub fn string_repr(&self, column: &str) -> StringRepr {
+        let borrowed = match self.string_repr {
+            StringRepr::Cow => StringRepr::Cow,
+            _ => StringRepr::Str,
+        };
+
+        if self.own.iter().any(|c| c == column) {
+            StringRepr::String
+        } else if self.borrow.iter().any(|c| c == column) {
+            borrowed
+        } else if self.owned {
+            StringRepr::String
+        } else {
+            self.string_repr
+        }
+    }
+
+    /// Returns a file to write the code to, `None` means stdout.
+    pub fn output_path(&self) -> Result<Option<PathBuf>> {
+        match (&self.output, &self.out_dir) {
+            (Some(path), _) => Ok(Some(path.clone())),
+            (None, Some(_)) if self.table.is_empty() => {
+                bail!("`--out-dir` requires the table, use `-o` for queries")
+            }
+            (None, Some(dir)) => Ok(Some(dir.join(format!("{}.rs", self.table)))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Formats options affecting the generated code, `--check`, `--watch`
+    /// and output paths are omitted.
     pub fn format(&self) -> String {
         let mut s = String::new();
 
-        let _ = write!(&mut s, "ch2rs {}", self.table);
+        s.push_str("ch2rs");
 
-        if self.database != "default" {
-            let _ = write!(&mut s, " -d {}", self.database);
+        if !self.table.is_empty() {
+            let _ = write!(&mut s, " {}", quote(&self.table));
         }
 
+        if let Some(query) = &self.query {
+            let _ = write!(&mut s, " --query {}", quote_always(query));
+        }
+
+        if let Some(query_file) = &self.query_file {
+            let path = query_file.to_string_lossy();
+            let _ = write!(&mut s, " --query-file {}", quote(&path));
+        }
+
+        if let Some(ddl) = &self.ddl {
+            let _ = write!(&mut s, " --ddl {}", quote(&ddl.to_string_lossy()));
+        }
+
+        if let Some(schema_file) = &self.schema_file {
+            let path = schema_file.to_string_lossy();
+            let _ = write!(&mut s, " --schema-file {}", quote(&path));
+        }
+
+        if self.follow_mv {
+            s.push_str(" --follow-mv");
+        }
+
+        if self.dictionary {
+            s.push_str(" --dictionary");
+        }
+
+        if let Some(source) = &self.compatible_with {
+            let _ = write!(&mut s, " --compatible-with {}", quote(&source.to_string()));
+        }
+
+        if let Some(name) = &self.struct_name {
+            let _ = write!(&mut s, " --struct-name {}", quote(name));
+        }
+
+        // Always written, otherwise `CLICKHOUSE_DATABASE` would apply.
+        let _ = write!(&mut s, " -d {}", quote(&self.database));
+
         if self.serialize {
             s.push_str(" -S");
         }
@@ -98,13 +533,27 @@ impl Options {
             s.push_str(" --owned");
         }
 
+        if self.string_repr != StringRepr::Str {
+            let _ = write!(&mut s, " --string-repr {}", self.string_repr);
+        }
+
+        if self.verify_schema {
+            s.push_str(" --verify-schema");
+        }
+
+        if self.query_helpers {
+            s.push_str(" --query-helpers");
+        }
+
+        if self.inserter {
+            s.push_str(" --inserter");
+        }
+
         s.push_str(" \\\n");
 
         if !self.derives.is_empty() {
             for derive in &self.derives {
-                s.push_str("    --derive ");
-                s.push_str(derive);
-                s.push_str(" \\\n");
+                let _ = writeln!(&mut s, "    --derive {} \\", quote(derive));
             }
         }
 
@@ -113,7 +562,11 @@ impl Options {
         types.sort();
 
         for t in types {
-            let _ = writeln!(&mut s, "    -T '{}={}' \\", t.sql, t.type_);
+            let _ = writeln!(
+                &mut s,
+                "    -T {} \\",
+                quote_always(&format!("{}={}", t.sql, t.type_))
+            );
         }
 
         // -O
@@ -121,7 +574,11 @@ impl Options {
         overrides.sort();
 
         for o in overrides {
-            let _ = writeln!(&mut s, "    -O '{}={}' \\", o.column, o.type_);
+            let _ = writeln!(
+                &mut s,
+                "    -O {} \\",
+                quote_always(&format!("{}={}", o.column, o.type_))
+            );
         }
 
         // -B
@@ -129,7 +586,7 @@ impl Options {
         bytes.sort();
 
         for b in bytes {
-            let _ = writeln!(&mut s, "    -B '{}' \\", b);
+            let _ = writeln!(&mut s, "    -B {} \\", quote_always(b));
         }
 
         // -I
@@ -137,10 +594,89 @@ impl Options {
         ignore.sort();
 
         for i in ignore {
-            let _ = writeln!(&mut s, "    -I '{}' \\", i);
+            let _ = writeln!(&mut s, "    -I {} \\", quote_always(i));
+        }
+
+        // --borrow
+        let mut borrow = self.borrow.iter().collect::<Vec<_>>();
+        borrow.sort();
+
+        for b in borrow {
+            let _ = writeln!(&mut s, "    --borrow {} \\", quote_always(b));
+        }
+
+        // --own
+        let mut own = self.own.iter().collect::<Vec<_>>();
+        own.sort();
+
+        for o in own {
+            let _ = writeln!(&mut s, "    --own {} \\", quote_always(o));
         }
 
         s.trim_end_matches(|c| ['\\', ' ', '\n'].contains(&c))
             .into()
     }
+
+    /// Parses options from the header of code generated by ch2rs.
+    ///
+    /// Connection options aren't written to the header, so they're default.
+    /// The database is always written, so `CLICKHOUSE_DATABASE` doesn't apply.
+    pub fn from_header(code: &str) -> Result<Self> {
+        let header = crate::codegen::parse_options(code).context("there is no options header")?;
+        let args = split_args(header)?;
+
+        if args.first().map(String::as_str) != Some("ch2rs") {
+            bail!("the options header must start with `ch2rs`");
+        }
+
+        Self::from_iter_safe(args).map_err(|err| anyhow::anyhow!("{}", err.message))
+    }
+}
+
+// Quotes the argument for a shell if it contains special characters.
+fn quote(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:,=+@%".contains(c);
+
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.into()
+    } else {
+        quote_always(arg).into()
+    }
+}
+
+fn quote_always(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+// Splits a command line produced by `Options::format()` in the way a shell does.
+fn split_args(line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => bail!("unterminated quote"),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // A line continuation.
+                Some('\n') => {}
+                Some(c) => arg.get_or_insert_with(String::new).push(c),
+                None => bail!("unexpected end after `\\`"),
+            },
+            c if c.is_whitespace() => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    args.extend(arg);
+    Ok(args)
 }
@@ -0,0 +1,49 @@
+use std::{fmt, fs, path::Path, process};
+
+use anyhow::{Context, Result};
+
+/// What happened to the file written by [`crate::write_code`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WriteStatus {
+    Created,
+    Updated,
+    /// The content is the same, the file is left untouched.
+    Unchanged,
+}
+
+impl fmt::Display for WriteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WriteStatus::Created => "created",
+            WriteStatus::Updated => "updated",
+            WriteStatus::Unchanged => "unchanged",
+        })
+    }
+}
+
+pub(crate) fn write(path: &Path, code: &str) -> Result<WriteStatus> {
+    let code = format!("{}\n", code);
+
+    let status = match fs::read_to_string(path) {
+        Ok(actual) if actual == code => return Ok(WriteStatus::Unchanged),
+        Ok(_) => WriteStatus::Updated,
+        Err(_) => WriteStatus::Created,
+    };
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    // Write to a temporary file in the same directory and rename it,
+    // so readers never see a partially written file.
+    let name = path.file_name().context("the path must be a file")?;
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+
+    fs::write(&tmp, code).with_context(|| format!("failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| {
+        let _ = fs::remove_file(&tmp);
+        format!("failed to write {}", path.display())
+    })?;
+
+    Ok(status)
+}
@@ -1,19 +1,167 @@
+//! The model of ClickHouse tables the code is generated from.
+//!
+//! A [`Table`] is usually fetched from the server by [`crate::mine`], but it
+//! can also be built manually and passed to [`crate::generate_from_table`].
+
 use std::fmt;
 
-#[derive(Debug)]
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A table with its columns in the server order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct Table {
+    pub database: String,
+    pub name: String,
+    pub comment: String,
+    /// The engine's name, e.g. `ReplacingMergeTree`.
+    pub engine: String,
+    /// The engine with its parameters and clauses.
+    pub engine_full: String,
+    pub partition_key: String,
+    pub sorting_key: String,
+    pub primary_key: String,
+    /// The table's TTL expression, empty if there is no TTL.
+    pub ttl: String,
     pub columns: Vec<Column>,
+    /// Columns left out by `ignore`, they're still a part of the schema fingerprint.
+    #[serde(skip)]
+    pub ignored: Vec<IgnoredColumn>,
+}
+
+impl Table {
+    /// Creates a table without any metadata, columns are numbered in order.
+    pub fn new(
+        database: impl Into<String>,
+        name: impl Into<String>,
+        columns: impl IntoIterator<Item = Column>,
+    ) -> Self {
+        let columns = columns
+            .into_iter()
+            .zip(1..)
+            .map(|(column, position)| Column { position, ..column })
+            .collect();
+
+        Self {
+            database: database.into(),
+            name: name.into(),
+            columns,
+            ..Self::default()
+        }
+    }
+
+    /// Moves columns with the provided names to `ignored`.
+    pub(crate) fn ignore(&mut self, names: &[String]) {
+        let (ignored, columns) = self
+            .columns
+            .drain(..)
+            .partition::<Vec<_>, _>(|c| names.contains(&c.name));
+
+        self.columns = columns;
+        self.ignored.extend(ignored.iter().map(IgnoredColumn::new));
+    }
 }
 
-#[derive(Debug)]
+/// A column with its metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Column {
     pub name: String,
+    #[serde(rename = "type")]
     pub type_: SqlType,
+    #[serde(default)]
     pub comment: String,
+    /// 1-based position of the column in the table.
+    pub position: u64,
+    #[serde(default)]
+    pub default_kind: Option<DefaultKind>,
+    #[serde(default)]
+    pub default_expression: String,
+    #[serde(default)]
+    pub is_in_partition_key: bool,
+    #[serde(default)]
+    pub is_in_sorting_key: bool,
+    #[serde(default)]
+    pub is_in_primary_key: bool,
+    #[serde(default)]
+    pub compression_codec: String,
+}
+
+impl Column {
+    /// Creates a column without any metadata.
+    pub fn new(name: impl Into<String>, type_: SqlType) -> Self {
+        Self {
+            name: name.into(),
+            type_,
+            comment: String::new(),
+            position: 0,
+            default_kind: None,
+            default_expression: String::new(),
+            is_in_partition_key: false,
+            is_in_sorting_key: false,
+            is_in_primary_key: false,
+            compression_codec: String::new(),
+        }
+    }
+}
+
+/// A column left out by `ignore`, its type may be unsupported.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct IgnoredColumn {
+    pub name: String,
+    /// The type as written by the server.
+    pub type_: String,
+    /// 1-based position of the column in the table.
+    pub position: u64,
+}
+
+impl IgnoredColumn {
+    pub(crate) fn new(column: &Column) -> Self {
+        Self {
+            name: column.name.clone(),
+            type_: column.type_.to_string(),
+            position: column.position,
+        }
+    }
+}
+
+/// A kind of the column's default expression.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+#[non_exhaustive]
+pub enum DefaultKind {
+    Default,
+    Materialized,
+    Alias,
+    Ephemeral,
+}
+
+impl DefaultKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DefaultKind::Default => "DEFAULT",
+            DefaultKind::Materialized => "MATERIALIZED",
+            DefaultKind::Alias => "ALIAS",
+            DefaultKind::Ephemeral => "EPHEMERAL",
+        }
+    }
+}
+
+impl fmt::Display for DefaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
+/// A ClickHouse type.
+///
+/// Wrappers that don't affect the representation, e.g. `LowCardinality`, are
+/// omitted. Use [`crate::parse_type`] to build it from a string.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
 pub enum SqlType {
     UInt8,
     UInt16,
@@ -31,11 +179,14 @@ pub enum SqlType {
     Float32,
     Float64,
     Date,
+    /// `DateTime` with an optional quoted timezone, e.g. `'UTC'`.
     DateTime(Option<String>),
+    /// `DateTime64` with a precision and an optional quoted timezone.
     DateTime64(u32, Option<String>),
     IPv4,
     IPv6,
     UUID,
+    /// `Decimal` with a precision and a scale.
     Decimal(u32, u32),
     Enum8(Vec<(String, i32)>),
     Enum16(Vec<(String, i32)>),
@@ -45,14 +196,69 @@ pub enum SqlType {
     Nullable(Box<SqlType>),
 }
 
+/// Formats the type in the way ClickHouse does.
 impl fmt::Display for SqlType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_list<T>(
+            f: &mut fmt::Formatter<'_>,
+            items: &[T],
+            write: impl Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(", ")?;
+                }
+                write(f, item)?;
+            }
+            Ok(())
+        }
+
+        fn write_variant(f: &mut fmt::Formatter<'_>, (name, value): &(String, i32)) -> fmt::Result {
+            let name = name.replace('\\', "\\\\").replace('\'', "\\'");
+            write!(f, "'{}' = {}", name, value)
+        }
+
         match self {
+            SqlType::FixedString(size) => write!(f, "FixedString({})", size),
             SqlType::DateTime(Some(tz)) => write!(f, "DateTime({})", tz),
             SqlType::DateTime(None) => f.write_str("DateTime"),
             SqlType::DateTime64(prec, Some(tz)) => write!(f, "DateTime64({}, {})", prec, tz),
             SqlType::DateTime64(prec, None) => write!(f, "DateTime64({})", prec),
+            SqlType::Decimal(prec, scale) => write!(f, "Decimal({}, {})", prec, scale),
+            SqlType::Enum8(variants) => {
+                f.write_str("Enum8(")?;
+                write_list(f, variants, write_variant)?;
+                f.write_str(")")
+            }
+            SqlType::Enum16(variants) => {
+                f.write_str("Enum16(")?;
+                write_list(f, variants, write_variant)?;
+                f.write_str(")")
+            }
+            SqlType::Array(inner) => write!(f, "Array({})", inner),
+            SqlType::Tuple(inner) => {
+                f.write_str("Tuple(")?;
+                write_list(f, inner, |f, t| write!(f, "{}", t))?;
+                f.write_str(")")
+            }
+            SqlType::Map(key, value) => write!(f, "Map({}, {})", key, value),
+            SqlType::Nullable(inner) => write!(f, "Nullable({})", inner),
             _ => fmt::Debug::fmt(self, f),
         }
     }
 }
+
+/// Serialized in the ClickHouse syntax, e.g. `"Array(String)"`.
+impl Serialize for SqlType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SqlType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        crate::parse_type(&raw)
+            .map_err(|err| D::Error::custom(format!("invalid type `{}`: {:#}", raw, err)))
+    }
+}
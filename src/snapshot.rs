@@ -0,0 +1,85 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{options::Options, schema::Table};
+
+/// Tables written by `ch2rs dump` to generate code without a server.
+///
+/// Stored as pretty-printed JSON with a format version, so that snapshots can
+/// be committed and reviewed along with the code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Snapshot {
+    pub version: u32,
+    pub tables: Vec<Table>,
+}
+
+impl Snapshot {
+    /// The version of the format written by this version of ch2rs.
+    pub const VERSION: u32 = 1;
+
+    pub fn new(tables: impl IntoIterator<Item = Table>) -> Self {
+        Self {
+            version: Self::VERSION,
+            tables: tables.into_iter().collect(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+
+        let header: Header = serde_json::from_str(json).context("invalid snapshot")?;
+        if header.version != Self::VERSION {
+            bail!(
+                "unsupported snapshot version {}, expected {}",
+                header.version,
+                Self::VERSION
+            );
+        }
+
+        serde_json::from_str(json).context("invalid snapshot")
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("infallible");
+        json.push('\n');
+        json
+    }
+
+    /// Reads the snapshot from the file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Returns the table by its database and name.
+    pub fn table(&self, database: &str, name: &str) -> Option<&Table> {
+        self.tables
+            .iter()
+            .find(|t| t.database == database && t.name == name)
+    }
+}
+
+/// Reads the table requested in options from the snapshot.
+pub(crate) fn load(path: &Path, options: &Options) -> Result<Table> {
+    let snapshot = Snapshot::read(path)?;
+    let Some(table) = snapshot.table(&options.database, &options.table) else {
+        bail!(
+            "there is no `{}.{}` table in {}",
+            options.database,
+            options.table,
+            path.display()
+        );
+    };
+
+    let mut table = table.clone();
+    table.ignore(&options.ignore);
+    Ok(table)
+}
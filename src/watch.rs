@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use clickhouse::Client;
+
+use crate::{codegen, config::Target, miner, output::WriteStatus};
+
+/// Regenerates targets when their schemas change.
+///
+/// Targets are generated on every poll and tracked by the schema fingerprint
+/// of the code, so every source is covered: the target of `--follow-mv`,
+/// local tables of Distributed ones and the source of `--compatible-with`.
+/// Files are rewritten only if the fingerprint has changed.
+pub struct Watcher {
+    targets: Vec<Watched>,
+}
+
+struct Watched {
+    target: Target,
+    /// `None` for DDL and snapshots, they're generated without a client.
+    client: Option<Box<Client>>,
+    state: State,
+}
+
+#[derive(PartialEq, Eq)]
+enum State {
+    Initial,
+    /// The fingerprint of the written code.
+    Written(String),
+    /// The error of the last poll, the target is retried on every poll.
+    Failed(String),
+}
+
+impl Watcher {
+    pub fn new(targets: Vec<Target>) -> Result<Self> {
+        let targets = targets
+            .into_iter()
+            .map(|target| {
+                let client = make_client(&target)
+                    .with_context(|| format!("failed to watch {}", target.output.display()))?;
+
+                Ok(Watched {
+                    target,
+                    client: client.map(Box::new),
+                    state: State::Initial,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { targets })
+    }
+
+    /// Regenerates targets whose schemas have changed since the previous
+    /// poll, every target is generated on the first poll.
+    ///
+    /// Failed targets are retried on every poll, but the same error
+    /// is reported only once.
+    pub async fn poll(&mut self) -> Vec<(&Target, Result<WriteStatus>)> {
+        let mut results = Vec::new();
+
+        for watched in &mut self.targets {
+            match watched.regenerate().await {
+                Ok(None) => {}
+                Ok(Some((status, fingerprint))) => {
+                    watched.state = State::Written(fingerprint);
+                    results.push((&watched.target, Ok(status)));
+                }
+                Err(err) => {
+                    let state = State::Failed(format!("{:#}", err));
+                    if watched.state != state {
+                        watched.state = state;
+                        results.push((&watched.target, Err(err)));
+                    }
+                }
+            }
+        }
+
+        results
+    }
+}
+
+impl Watched {
+    // Returns `None` if the fingerprint hasn't changed since the last write.
+    async fn regenerate(&self) -> Result<Option<(WriteStatus, String)>> {
+        let client = self.client.as_deref();
+        let code = crate::generate_with(self.target.options.clone(), client).await?;
+        let fingerprint = codegen::parse_fingerprint(&code)
+            .context("generated code has no fingerprint")?
+            .to_string();
+
+        if self.state == State::Written(fingerprint.clone()) {
+            return Ok(None);
+        }
+
+        let status = crate::write_code(&self.target.output, &code)?;
+        Ok(Some((status, fingerprint)))
+    }
+}
+
+fn make_client(target: &Target) -> Result<Option<Client>> {
+    let options = &target.options;
+
+    if options.query.is_some() || options.query_file.is_some() {
+        return miner::make_client(&options.connection).map(Some);
+    }
+
+    if options.ddl.as_deref() == Some(Path::new("-")) {
+        bail!("DDL can't be read from stdin in the watch mode");
+    }
+
+    if options.table.is_empty() {
+        bail!("the table or the query must be specified");
+    }
+
+    if options.ddl.is_some() || options.schema_file.is_some() {
+        return Ok(None);
+    }
+
+    miner::make_client(&options.connection).map(Some)
+}
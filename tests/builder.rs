@@ -0,0 +1,95 @@
+use std::{env, fs, io::Read, net::TcpListener, sync::mpsc, thread, time::Duration};
+
+mod common;
+
+#[test]
+fn write_to() {
+    let dir = common::temp_path("builder");
+    fs::create_dir_all(&dir).expect("failed to create a directory");
+
+    ch2rs::Builder::new()
+        .ddl_file("tests/schema/events.sql")
+        .table("events")
+        .deserialize(true)
+        .owned(true)
+        .type_("DateTime", "u32")
+        .bytes("payload")
+        .override_type("payload", "Vec<u8>")
+        .derive("Clone")
+        .write_to(&dir)
+        .expect("failed to generate");
+
+    let code = fs::read_to_string(dir.join("events.rs")).expect("failed to read the file");
+    fs::remove_dir_all(&dir).expect("failed to remove a directory");
+
+    assert!(!code.contains("#!["), "inner attributes break `include!`");
+    assert!(code.contains("#[allow(warnings, clippy::all)]\npub struct Row {"));
+    assert!(code.contains("#[allow(warnings, clippy::all)]\nimpl Row {"));
+    assert!(code.contains("pub struct Row {"));
+    assert!(code.contains("pub timestamp: u32,"));
+    assert!(code.contains("pub kind: Kind,"));
+    assert!(code.contains("pub user: String,"));
+    assert!(code.contains("pub payload: Vec<u8>,"));
+}
+
+#[test]
+fn connection() {
+    // The server records the request, but never responds.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut streams = Vec::new();
+        for mut stream in listener.incoming().flatten() {
+            let mut buf = [0; 4096];
+            let len = stream.read(&mut buf).unwrap_or(0);
+            let _ = tx.send(String::from_utf8_lossy(&buf[..len]).into_owned());
+            streams.push(stream);
+        }
+    });
+
+    let err = ch2rs::Builder::new()
+        .url(url)
+        .table("events")
+        .timeout(Duration::from_secs(1))
+        .compression(false)
+        .write_to(env::temp_dir())
+        .unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "failed to load the `events` table: timed out after 1 seconds"
+    );
+
+    let request = rx.recv().expect("no request");
+    let line = request.lines().next().unwrap();
+    assert!(line.contains("max_execution_time=1"), "{}", line);
+    assert!(!line.contains("compress=1"), "{}", line);
+}
+
+#[test]
+fn within_runtime() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let err = runtime.block_on(async {
+        ch2rs::Builder::new()
+            .ddl_file("tests/schema/events.sql")
+            .table("events")
+            .write_to(env::temp_dir())
+            .unwrap_err()
+    });
+    assert_eq!(
+        err.to_string(),
+        "`write_to` blocks the thread, so it can't be called within a tokio runtime"
+    );
+}
+
+#[test]
+fn debug_redacts_password() {
+    let builder = ch2rs::Builder::new().user("reader").password("hunter2");
+    let debug = format!("{:?}", builder);
+    assert!(debug.contains("\"reader\""));
+    assert!(debug.contains("<redacted>"));
+    assert!(!debug.contains("hunter2"));
+}
@@ -0,0 +1,184 @@
+use std::{env, fs};
+
+use ch2rs::{Options, Override};
+
+mod common;
+
+#[tokio::test(flavor = "current_thread")]
+async fn check() {
+    let path = common::temp_path("check").with_extension("rs");
+    let code = ch2rs::generate(common::options("events"))
+        .await
+        .expect("failed to generate");
+    fs::write(&path, format!("{}\n", code)).expect("failed to write the file");
+
+    assert!(code.contains("// Schema fingerprint: "));
+    ch2rs::check(&path, &code).expect("the same code must pass");
+
+    // Only options are changed.
+    let mut changed = common::options("events");
+    changed.owned = true;
+    let code = ch2rs::generate(changed).await.expect("failed to generate");
+    let err = format!("{}", ch2rs::check(&path, &code).unwrap_err());
+    assert!(err.contains("is outdated, the code has changed"), "{}", err);
+    assert!(err.contains("-    pub user: &'a str,"), "{}", err);
+    assert!(err.contains("+    pub user: String,"), "{}", err);
+
+    // Ignored columns are still a part of the schema.
+    let mut changed = common::options("events");
+    changed.ignore = vec!["payload".into()];
+    let code = ch2rs::generate(changed).await.expect("failed to generate");
+    let err = format!("{}", ch2rs::check(&path, &code).unwrap_err());
+    assert!(err.contains("is outdated, the code has changed"), "{}", err);
+
+    // A type of the column is changed.
+    let ddl = common::temp_path("check").with_extension("sql");
+    let sql = fs::read_to_string("tests/schema/events.sql").expect("failed to read DDL");
+    fs::write(&ddl, sql.replace("payload   String", "payload   UInt64"))
+        .expect("failed to write DDL");
+    let mut changed = common::options("events");
+    changed.ddl = Some(ddl.clone());
+    let code = ch2rs::generate(changed).await.expect("failed to generate");
+    fs::remove_file(&ddl).expect("failed to remove DDL");
+    let err = format!("{}", ch2rs::check(&path, &code).unwrap_err());
+    assert!(
+        err.contains("is outdated, the schema has changed"),
+        "{}",
+        err
+    );
+    assert!(err.contains("-// Schema fingerprint: "), "{}", err);
+
+    fs::remove_file(&path).expect("failed to remove the file");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn check_file() {
+    let path = common::temp_path("check-file").with_extension("rs");
+    let code = ch2rs::generate(common::options("events"))
+        .await
+        .expect("failed to generate");
+    fs::write(&path, format!("{}\n", code)).expect("failed to write the file");
+
+    let connection = ch2rs::Connection::default();
+    ch2rs::check_file(&path, &connection)
+        .await
+        .expect("options are taken from the header");
+
+    // The code is edited by hand.
+    fs::write(&path, code.replace("pub user:", "pub login:")).expect("failed to write the file");
+    let err = ch2rs::check_file(&path, &connection).await.unwrap_err();
+    fs::remove_file(&path).expect("failed to remove the file");
+
+    let err = format!("{}", err);
+    assert!(err.contains("is outdated, the code has changed"), "{}", err);
+    assert!(err.contains("+    pub user: &'a str,"), "{}", err);
+}
+
+#[test]
+fn options_header() {
+    let options = Options {
+        table: "events".into(),
+        database: "my db".into(),
+        query: Some("SELECT 'a'\n  FROM t -- it's a comment".into()),
+        derives: vec!["Clone".into()],
+        overrides: vec![Override {
+            column: "user".into(),
+            type_: "Cow<'a, str>".into(),
+        }],
+        ignore: vec!["payload".into()],
+        serialize: true,
+        ..Options::default()
+    };
+
+    let code = format!(
+        "// Generated with the following options:\n/*\n{}\n*/",
+        options.format()
+    );
+    let parsed = Options::from_header(&code).expect("failed to parse the header");
+    assert_eq!(parsed.format(), options.format());
+    assert_eq!(parsed.database, "my db");
+    assert_eq!(parsed.query, options.query);
+    assert_eq!(parsed.overrides, options.overrides);
+
+    let err = Options::from_header("fn main() {}").unwrap_err();
+    assert_eq!(err.to_string(), "there is no options header");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn regen() {
+    let path = common::temp_path("regen").with_extension("rs");
+    let code = ch2rs::generate(common::options("events"))
+        .await
+        .expect("failed to generate");
+    fs::write(&path, format!("{}\n", code)).expect("failed to write the file");
+
+    let connection = ch2rs::Connection::default();
+    let status = ch2rs::regen(&path, &connection).await.unwrap();
+    assert_eq!(status, ch2rs::WriteStatus::Unchanged);
+
+    // Options are edited by hand.
+    let edited = code.replace(" -D ", " -S ");
+    fs::write(&path, edited).expect("failed to write the file");
+    let status = ch2rs::regen(&path, &connection).await.unwrap();
+    assert_eq!(status, ch2rs::WriteStatus::Updated);
+
+    let code = fs::read_to_string(&path).expect("failed to read the file");
+    fs::remove_file(&path).expect("failed to remove the file");
+    assert!(code.contains("#[derive(Debug, clickhouse::Row, serde::Serialize)]"));
+}
+
+// Headers always contain `-d`, so other tests aren't affected by the variable.
+#[tokio::test(flavor = "current_thread")]
+async fn database_from_env() {
+    let path = common::temp_path("database").with_extension("rs");
+    let code = ch2rs::generate(common::options("events"))
+        .await
+        .expect("failed to generate");
+    fs::write(&path, format!("{}\n", code)).expect("failed to write the file");
+    assert!(code.contains("ch2rs events --ddl tests/schema/events.sql -d default -D"));
+
+    env::set_var("CLICKHOUSE_DATABASE", "analytics");
+    let parsed = Options::from_header(&code).expect("failed to parse the header");
+    let connection = ch2rs::Connection::default();
+    let checked = ch2rs::check_file(&path, &connection).await;
+    let status = ch2rs::regen(&path, &connection).await;
+    env::remove_var("CLICKHOUSE_DATABASE");
+    fs::remove_file(&path).expect("failed to remove the file");
+
+    assert_eq!(parsed.database, "default");
+    checked.expect("the header must be self-contained");
+    assert_eq!(status.unwrap(), ch2rs::WriteStatus::Unchanged);
+}
+
+#[test]
+fn write_code() {
+    use ch2rs::WriteStatus;
+
+    let dir = common::temp_path("write");
+    let path = dir.join("rows/events.rs");
+
+    assert_eq!(
+        ch2rs::write_code(&path, "v1").unwrap(),
+        WriteStatus::Created
+    );
+    let mtime = fs::metadata(&path).and_then(|m| m.modified()).unwrap();
+
+    assert_eq!(
+        ch2rs::write_code(&path, "v1").unwrap(),
+        WriteStatus::Unchanged
+    );
+    assert_eq!(
+        fs::metadata(&path).and_then(|m| m.modified()).unwrap(),
+        mtime
+    );
+
+    assert_eq!(
+        ch2rs::write_code(&path, "v2").unwrap(),
+        WriteStatus::Updated
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "v2\n");
+
+    // No temporary files are left.
+    assert_eq!(fs::read_dir(dir.join("rows")).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).expect("failed to remove a directory");
+}
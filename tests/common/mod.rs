@@ -0,0 +1,27 @@
+// Every test binary compiles the module, but uses only a part of it.
+#![allow(dead_code)]
+
+use std::{env, path::PathBuf, process};
+
+use ch2rs::{Options, Type};
+
+/// Returns `ch2rs-<name>-<pid>` in the temporary directory, so concurrent
+/// runs of tests don't clash.
+pub fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("ch2rs-{}-{}", name, process::id()))
+}
+
+/// Options generating a deserializable row from `tests/schema/<table>.sql`
+/// with `DateTime` as `u32`.
+pub fn options(table: &str) -> Options {
+    Options {
+        table: table.into(),
+        ddl: Some(format!("tests/schema/{}.sql", table).into()),
+        deserialize: true,
+        types: vec![Type {
+            sql: ch2rs::parse_type("DateTime").unwrap(),
+            type_: "u32".into(),
+        }],
+        ..Options::default()
+    }
+}
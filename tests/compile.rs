@@ -0,0 +1,66 @@
+use std::fs;
+
+use ch2rs::Options;
+
+mod common;
+
+fn options(name: &str) -> Options {
+    Options {
+        serialize: true,
+        owned: true,
+        query_helpers: true,
+        ..common::options(name)
+    }
+}
+
+// Unlike snapshots, it doesn't require a server, the code is generated from DDL.
+#[tokio::test(flavor = "current_thread")]
+async fn query_helpers() {
+    let cases = [
+        ("helpers", options("events")),
+        (
+            "inserter",
+            Options {
+                inserter: true,
+                ..options("events")
+            },
+        ),
+        // Neither an enum of the column nor the struct clashes with `COLUMNS`.
+        ("column", options("column")),
+        (
+            "struct_name",
+            Options {
+                struct_name: Some("Column".into()),
+                ..options("events")
+            },
+        ),
+        (
+            "verify_schema",
+            Options {
+                verify_schema: true,
+                ignore: vec!["payload".into()],
+                ..options("events")
+            },
+        ),
+        (
+            "borrowed",
+            Options {
+                owned: false,
+                inserter: true,
+                ..options("events")
+            },
+        ),
+    ];
+
+    fs::create_dir_all("target/compile").expect("failed to create target/compile");
+
+    for (name, options) in cases {
+        let code = ch2rs::generate(options).await.expect("failed to generate");
+        let path = format!("target/compile/{}.rs", name);
+        fs::write(&path, format!("{}\nfn main() {{}}\n", code))
+            .expect("failed to write the source file");
+    }
+
+    let t = trybuild::TestCases::new();
+    t.pass("target/compile/*.rs");
+}
@@ -0,0 +1,189 @@
+use std::{fs, path::Path};
+
+use ch2rs::Config;
+
+mod common;
+
+const CONFIG: &str = r#"
+deserialize = true
+owned = true
+verify-schema = true
+derives = ["Clone"]
+
+[connection]
+url = "localhost:9000"
+user = "reader"
+
+[types]
+DateTime = "u32"
+
+[tables.events]
+struct = "Event"
+output = "rows/events.rs"
+ddl = "events.sql"
+overrides = { user = "Box<str>" }
+ignore = ["payload"]
+derives = ["PartialEq"]
+
+[tables."analytics.sessions"]
+output = "rows/sessions.rs"
+bytes = ["blob"]
+"#;
+
+#[test]
+fn targets() {
+    let config = Config::from_toml(CONFIG).expect("failed to parse");
+    let targets = config.targets().expect("invalid targets");
+    assert_eq!(targets.len(), 2);
+
+    let sessions = &targets[0];
+    assert_eq!(sessions.output.to_str(), Some("rows/sessions.rs"));
+    assert_eq!(sessions.options.database, "analytics");
+    assert_eq!(sessions.options.table, "sessions");
+    assert_eq!(sessions.options.bytes, ["blob"]);
+    assert_eq!(
+        sessions.options.connection.url.as_deref(),
+        Some("localhost:9000")
+    );
+    assert_eq!(sessions.options.connection.user.as_deref(), Some("reader"));
+
+    let events = &targets[1];
+    assert_eq!(events.options.database, "default");
+    assert_eq!(events.options.struct_name.as_deref(), Some("Event"));
+    assert_eq!(events.options.derives, ["Clone", "PartialEq"]);
+    assert!(events.options.deserialize && events.options.owned);
+    assert!(events.options.verify_schema);
+
+    let err = Config::from_toml("[tables.events]\nunknown = 1").unwrap_err();
+    assert!(format!("{:#}", err).contains("unknown field `unknown`"));
+
+    let config = Config::from_toml("[tables.events]").unwrap();
+    let err = config.targets().unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "invalid settings of the `events` table: `output` or global `out-dir` must be specified"
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn generate_target() {
+    let dir = common::temp_path("config");
+    fs::create_dir_all(&dir).expect("failed to create a directory");
+    fs::copy("tests/schema/events.sql", dir.join("events.sql")).expect("failed to copy");
+
+    let config = CONFIG.split("[tables.\"analytics").next().unwrap();
+    fs::write(dir.join("ch2rs.toml"), config).expect("failed to write the config");
+
+    let targets = Config::read(dir.join("ch2rs.toml"))
+        .and_then(|config| config.targets())
+        .expect("invalid config");
+    assert_eq!(targets[0].output, dir.join("rows/events.rs"));
+
+    let status = ch2rs::generate_target(&targets[0]).await.unwrap();
+    assert_eq!(status, ch2rs::WriteStatus::Created);
+    let status = ch2rs::generate_target(&targets[0]).await.unwrap();
+    assert_eq!(status, ch2rs::WriteStatus::Unchanged);
+
+    let code = fs::read_to_string(dir.join("rows/events.rs")).expect("failed to read the file");
+    fs::remove_dir_all(&dir).expect("failed to remove a directory");
+
+    assert!(code.contains("pub struct Event {"));
+    assert!(code.contains("impl Event {"));
+    assert!(
+        code.contains("#[derive(Debug, clickhouse::Row, serde::Deserialize, Clone, PartialEq)]")
+    );
+    assert!(code.contains("pub user: Box<str>,"));
+    assert!(!code.contains("pub payload"));
+    assert!(code.contains("impl Event {\n    /// Compares columns of the table"));
+}
+
+#[test]
+fn read_tls_paths() {
+    let dir = common::temp_path("config-tls");
+    fs::create_dir_all(&dir).expect("failed to create a directory");
+
+    let config = r#"
+        [connection]
+        ca-cert = "certs/ca.pem"
+        client-cert = "certs/client.pem"
+        client-key = "/etc/ch2rs/client.key"
+    "#;
+    fs::write(dir.join("ch2rs.toml"), config).expect("failed to write the config");
+
+    let config = Config::read(dir.join("ch2rs.toml")).expect("invalid config");
+    fs::remove_dir_all(&dir).expect("failed to remove a directory");
+
+    let connection = &config.connection;
+    assert_eq!(connection.ca_cert, Some(dir.join("certs/ca.pem")));
+    assert_eq!(connection.client_cert, Some(dir.join("certs/client.pem")));
+    assert_eq!(
+        connection.client_key.as_deref(),
+        Some(Path::new("/etc/ch2rs/client.key"))
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn connection_options() {
+    use structopt::StructOpt;
+
+    let options = ch2rs::Options::from_iter([
+        "ch2rs",
+        "events",
+        "--setting",
+        "flatten_nested=0",
+        "--header",
+        "X-Team: analytics",
+        "--timeout",
+        "30",
+        "--no-compression",
+        "--role",
+        "reader",
+        "--ca-cert",
+        "tests/schema/missing.pem",
+    ]);
+
+    let connection = &options.connection;
+    assert_eq!(connection.settings, [("flatten_nested".into(), "0".into())]);
+    assert_eq!(connection.headers, [("X-Team".into(), "analytics".into())]);
+    assert_eq!(connection.timeout, Some(30));
+    assert!(connection.no_compression);
+    assert_eq!(connection.role.as_deref(), Some("reader"));
+
+    let err = ch2rs::mine(&options).await.unwrap_err();
+    assert_eq!(err.to_string(), "failed to read tests/schema/missing.pem");
+
+    // The server accepts connections, but never responds.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let mut streams = Vec::new();
+        loop {
+            streams.push(listener.accept().await);
+        }
+    });
+
+    let options = ch2rs::Options::from_iter(["ch2rs", "events", "-U", &url, "--timeout", "1"]);
+    let err = ch2rs::mine(&options).await.unwrap_err();
+    assert_eq!(err.to_string(), "timed out after 1 seconds");
+    server.abort();
+
+    let config = Config::from_toml(
+        r#"
+        [connection]
+        settings = { flatten_nested = 0, allow_experimental_object_type = true }
+        headers = { X-Team = "analytics" }
+        client-cert = "client.pem"
+        client-key = "client.key"
+        "#,
+    )
+    .expect("failed to parse");
+
+    let settings = &config.connection.settings;
+    assert_eq!(
+        settings[0],
+        ("allow_experimental_object_type".into(), "1".into())
+    );
+    assert_eq!(settings[1], ("flatten_nested".into(), "0".into()));
+    assert_eq!(config.connection.headers[0].1, "analytics");
+    assert!(config.connection.client_key.is_some());
+}
@@ -0,0 +1,111 @@
+use std::{env, fs};
+
+use ch2rs::Connection;
+
+mod common;
+
+const CLIENT_CONFIG: &str = "
+<config>
+    <host>ch.example.com</host>
+    <port>9440</port>
+    <secure>1</secure>
+    <user>from_config</user>
+    <password>secret</password>
+</config>
+";
+
+// Environment variables are shared, so it's the only test in the binary.
+#[test]
+fn resolve() {
+    let home = common::temp_path("home");
+    fs::create_dir_all(home.join(".clickhouse-client")).expect("failed to create a directory");
+    env::set_var("HOME", &home);
+    for name in ["CLICKHOUSE_URL", "CLICKHOUSE_USER", "CLICKHOUSE_PASSWORD"] {
+        env::remove_var(name);
+    }
+
+    // Defaults.
+    let resolved = Connection::default().resolve().unwrap();
+    assert_eq!(resolved.url.as_deref(), Some("localhost:8123"));
+    assert_eq!(resolved.user, None);
+    assert_eq!(resolved.password, None);
+
+    // The config of clickhouse-client.
+    let path = home.join(".clickhouse-client/config.xml");
+    fs::write(&path, CLIENT_CONFIG).expect("failed to write the config");
+    let resolved = Connection::default().resolve().unwrap();
+    assert_eq!(resolved.url.as_deref(), Some("https://ch.example.com:8443"));
+    assert_eq!(resolved.user.as_deref(), Some("from_config"));
+    assert_eq!(resolved.password.as_deref(), Some("secret"));
+
+    // Environment variables take precedence over the config, empty ones are ignored.
+    // The config's password isn't sent on behalf of another user.
+    env::set_var("CLICKHOUSE_URL", "http://env:8123");
+    env::set_var("CLICKHOUSE_USER", "from_env");
+    env::set_var("CLICKHOUSE_PASSWORD", "");
+    let resolved = Connection::default().resolve().unwrap();
+    assert_eq!(resolved.url.as_deref(), Some("http://env:8123"));
+    assert_eq!(resolved.user.as_deref(), Some("from_env"));
+    assert_eq!(resolved.password, None);
+
+    env::set_var("CLICKHOUSE_PASSWORD", "env_secret");
+    let resolved = Connection::default().resolve().unwrap();
+    assert_eq!(resolved.password.as_deref(), Some("env_secret"));
+
+    // Explicit options take precedence over everything.
+    let explicit = Connection {
+        user: Some("explicit".into()),
+        password: Some("".into()),
+        ..Connection::default()
+    };
+    let resolved = explicit.resolve().unwrap();
+    assert_eq!(resolved.url.as_deref(), Some("http://env:8123"));
+    assert_eq!(resolved.user.as_deref(), Some("explicit"));
+    assert_eq!(resolved.password.as_deref(), Some(""));
+
+    let explicit = Connection {
+        url: Some("http://explicit:8123".into()),
+        ..explicit
+    };
+    let resolved = explicit.resolve().unwrap();
+    assert_eq!(resolved.url.as_deref(), Some("http://explicit:8123"));
+
+    // The result is resolved, so resolving it again changes nothing.
+    env::set_var("CLICKHOUSE_USER", "changed");
+    let again = resolved.resolve().unwrap();
+    assert_eq!(again.url, resolved.url);
+    assert_eq!(again.user, resolved.user);
+    assert_eq!(again.password, resolved.password);
+    assert!(!again.ask_password);
+
+    // Without `<secure>` the plain HTTP port is used.
+    for name in ["CLICKHOUSE_URL", "CLICKHOUSE_USER", "CLICKHOUSE_PASSWORD"] {
+        env::remove_var(name);
+    }
+    fs::write(&path, "<config><host>plain</host></config>").expect("failed to write the config");
+    let resolved = Connection::default().resolve().unwrap();
+    assert_eq!(resolved.url.as_deref(), Some("http://plain:8123"));
+    assert_eq!(resolved.user, None);
+
+    // Only the password is taken with the default user.
+    fs::write(&path, "<config><password>secret</password></config>")
+        .expect("failed to write the config");
+    let resolved = Connection::default().resolve().unwrap();
+    assert_eq!(resolved.user, None);
+    assert_eq!(resolved.password.as_deref(), Some("secret"));
+
+    fs::write(&path, "<config>").expect("failed to write the config");
+    let err = Connection::default().resolve().unwrap_err();
+    assert!(err.to_string().starts_with("failed to parse"), "{}", err);
+
+    // The config isn't read if the URL and the user are known.
+    let explicit = Connection {
+        url: Some("http://explicit:8123".into()),
+        user: Some("explicit".into()),
+        ..Connection::default()
+    };
+    let resolved = explicit.resolve().unwrap();
+    assert_eq!(resolved.password, None);
+
+    fs::remove_dir_all(&home).expect("failed to remove a directory");
+}
@@ -0,0 +1,271 @@
+use ch2rs::{Options, Override};
+
+mod common;
+
+#[tokio::test(flavor = "current_thread")]
+async fn generate_from_ddl() {
+    let options = Options {
+        ddl: Some("tests/schema/aliases.sql".into()),
+        owned: true,
+        overrides: vec![Override {
+            column: "revenue".into(),
+            type_: "i64".into(),
+        }],
+        ..common::options("sessions")
+    };
+
+    let code = ch2rs::generate(options).await.expect("failed to generate");
+
+    assert!(code.contains("ch2rs sessions --ddl tests/schema/aliases.sql -d default -D --owned"));
+    assert!(code.contains("/// user sessions"));
+    assert!(code.contains("/// * Table: `analytics.sessions`"));
+    assert!(code.contains("/// * Engine: `ReplacingMergeTree`"));
+    assert!(code.contains("/// * Partition key: `toYYYYMM(started_at)`"));
+    assert!(code.contains("/// * Sorting key: `user_id, id`"));
+    assert!(code.contains("pub id: u64,"));
+    assert!(code.contains("pub user_id: i32,"));
+    assert!(code.contains("pub started_at: u32,"));
+    assert!(code.contains("pub duration: f64,"));
+    assert!(code.contains("pub country: Option<String>,"));
+    assert!(code.contains("pub is_bot: bool,"));
+    assert!(code.contains("pub revenue: i64,"));
+    assert!(code.contains("pub attempts: u8,"));
+    assert!(code.contains("pub raw_user_id: i32,"));
+    assert!(code.contains("pub referer: String,"));
+    assert!(code.contains("pub tags: (String, f32),"));
+
+    assert!(code.contains("pub const COLUMNS: &'static [Ch2rsColumn] = &["));
+    assert!(code.contains(
+        "name: \"is_bot\",\n            type_: \"Bool\",\n            \
+         default_kind: Some(\"MATERIALIZED\"),"
+    ));
+    assert!(code.contains(
+        "name: \"tags\",\n            type_: \"Tuple(String, Float32)\",\n            \
+         default_kind: None,"
+    ));
+    assert!(code.contains("pub fn column_names() -> impl Iterator<Item = &'static str> {"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn errors() {
+    let options = Options {
+        table: "unknown".into(),
+        ddl: Some("tests/schema/aliases.sql".into()),
+        ..Options::default()
+    };
+
+    let err = ch2rs::generate(options.clone()).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "there is no `CREATE TABLE unknown` statement"
+    );
+
+    let options = Options {
+        table: "untyped".into(),
+        ..options
+    };
+
+    let err = ch2rs::generate(options).await.unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "failed to parse the `untyped` table: failed to handle the `value` column: \
+         the type must be specified"
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn databases() {
+    let options = Options {
+        table: "events".into(),
+        database: "analytics".into(),
+        ddl: Some("tests/schema/databases.sql".into()),
+        owned: true,
+        ..Options::default()
+    };
+
+    let code = ch2rs::generate(options.clone())
+        .await
+        .expect("failed to generate");
+    assert!(code.contains("/// * Table: `analytics.events`"));
+    assert!(code.contains("pub kind: String,"));
+
+    let options = Options {
+        database: "db1".into(),
+        ..options
+    };
+
+    let code = ch2rs::generate(options.clone())
+        .await
+        .expect("failed to generate");
+    assert!(code.contains("/// * Table: `db1.events`"));
+    assert!(code.contains("pub id: u8,"));
+
+    let options = Options {
+        database: "default".into(),
+        ..options
+    };
+
+    let err = ch2rs::generate(options).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "there are several `CREATE TABLE events` statements, but none in the `default` database"
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn compatible_with() {
+    let options = Options {
+        table: "events".into(),
+        ddl: Some("tests/schema/events_v2.sql".into()),
+        compatible_with: Some("ddl:tests/schema/events.sql".parse().unwrap()),
+        deserialize: true,
+        overrides: vec![Override {
+            column: "timestamp".into(),
+            type_: "u32".into(),
+        }],
+        ..Options::default()
+    };
+
+    // `?fields` can't select a column absent in one of versions.
+    let err = ch2rs::generate(options.clone()).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the `ip` column exists only in one version, so it can't be read from both, use -I for it"
+    );
+
+    let options = Options {
+        ignore: vec!["ip".into(), "payload".into()],
+        ..options
+    };
+    let code = ch2rs::generate(options.clone())
+        .await
+        .expect("failed to generate");
+
+    assert!(code.contains("--compatible-with ddl:tests/schema/events.sql"));
+    assert!(code.contains("// Compatible with schema versions:\n//   "));
+    assert!(code.contains(" ddl:tests/schema/events_v2.sql\n//   "));
+    assert!(code.contains("pub user: &'a str,"));
+    assert!(code.contains("pub timestamp: u32,"));
+    assert!(!code.contains("pub ip"));
+    assert!(!code.contains("pub payload"));
+    assert!(!code.contains("serde(skip)"));
+
+    let from_header = ch2rs::Options::from_header(&code).unwrap();
+    assert_eq!(from_header.compatible_with, options.compatible_with);
+
+    // Types must have the same representation.
+    let path = common::temp_path("compatible").with_extension("sql");
+    let sql = std::fs::read_to_string("tests/schema/events.sql").unwrap();
+    std::fs::write(&path, sql.replace("user      String", "user      UInt64")).unwrap();
+
+    let source = format!("ddl:{}", path.display());
+    let err = ch2rs::generate(Options {
+        compatible_with: Some(source.parse().unwrap()),
+        ..options
+    })
+    .await
+    .unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        err.to_string(),
+        "the `user` column is `String` in one version and `UInt64` in another, use -O or -I for it"
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn verify_schema() {
+    let options = Options {
+        ignore: vec!["payload".into()],
+        verify_schema: true,
+        ..common::options("events")
+    };
+
+    let code = ch2rs::generate(options).await.expect("failed to generate");
+
+    assert!(
+        code.contains("ch2rs events --ddl tests/schema/events.sql -d default -D --verify-schema")
+    );
+    assert!(code.contains(
+        "    pub async fn verify_schema(
+        client: &clickhouse::Client,
+    ) -> Result<(), ch2rs_runtime::SchemaMismatch> {
+        use ch2rs_runtime::__private::verify_schema;
+        const IGNORED: &[&str] = &[\"payload\"];"
+    ));
+    assert!(code
+        .contains("verify_schema(client, Self::DATABASE, Self::TABLE, &expected, IGNORED).await"));
+    assert!(
+        code.contains("name: \"kind\",\n            type_: \"Enum8('click' = 1, 'view' = 2)\",")
+    );
+    assert!(!code.contains("name: \"payload\""));
+
+    // The runtime isn't copied into every file.
+    assert!(!code.contains("enum SchemaMismatch"));
+    assert!(!code.contains("fn normalize_type"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn query_helpers() {
+    let options = Options {
+        deserialize: false,
+        owned: true,
+        query_helpers: true,
+        ..common::options("events")
+    };
+
+    let code = ch2rs::generate(options.clone())
+        .await
+        .expect("failed to generate");
+
+    assert!(code
+        .contains("ch2rs events --ddl tests/schema/events.sql -d default --owned --query-helpers"));
+    assert!(code
+        .contains("pub const SELECT: &'static str = \"SELECT ?fields FROM `default`.`events`\";"));
+    assert!(code.contains(
+        "    pub fn insert(
+        client: &clickhouse::Client,
+    ) -> clickhouse::error::Result<clickhouse::insert::Insert<Self>> {
+        client.insert(Self::QUOTED_NAME)
+    }"
+    ));
+    assert!(code.contains("const QUOTED_NAME: &'static str = \"`default`.`events`\";"));
+    assert!(!code.contains("pub fn inserter("));
+    assert!(code.contains(
+        "    pub fn select(client: &clickhouse::Client) -> clickhouse::query::Query {
+        client.query(Self::SELECT)
+    }"
+    ));
+
+    let with_inserter = Options {
+        inserter: true,
+        ..options.clone()
+    };
+
+    let code = ch2rs::generate(with_inserter.clone())
+        .await
+        .expect("failed to generate");
+    assert!(code.contains("--query-helpers --inserter"));
+    assert!(code.contains("client.inserter(Self::QUOTED_NAME)"));
+
+    // Names are quoted.
+    let quoted = Options {
+        table: "1st-events".into(),
+        database: "order".into(),
+        ddl: Some("tests/schema/quoted.sql".into()),
+        ..with_inserter
+    };
+
+    let code = ch2rs::generate(quoted).await.expect("failed to generate");
+    assert!(code.contains("pub select: String,"));
+    assert!(code.contains("\"SELECT ?fields FROM `order`.`1st-events`\""));
+    assert!(code.contains("const QUOTED_NAME: &'static str = \"`order`.`1st-events`\";"));
+
+    let options = Options {
+        query_helpers: false,
+        ..options
+    };
+
+    let code = ch2rs::generate(options).await.expect("failed to generate");
+    assert!(!code.contains("pub fn select("));
+}
@@ -0,0 +1,148 @@
+use std::{env, fs};
+
+use ch2rs::{
+    schema::{Column, Table},
+    ChangeKind, Compatibility, DiffOptions, SchemaSource,
+};
+use structopt::StructOpt;
+
+mod common;
+
+fn table(columns: &[(&str, &str)]) -> Table {
+    let columns = columns
+        .iter()
+        .map(|(name, type_)| Column::new(*name, ch2rs::parse_type(type_).unwrap()));
+    Table::new("default", "events", columns)
+}
+
+fn changes(old: &Table, new: &Table) -> Vec<String> {
+    ch2rs::diff_tables(old, new)
+        .iter()
+        .map(|change| format!("{}: {}", change, change.compatibility))
+        .collect()
+}
+
+#[test]
+fn columns() {
+    let old = table(&[
+        ("a", "String"),
+        ("b", "UInt32"),
+        ("c", "Nullable(UInt32)"),
+        ("d", "String"),
+    ]);
+    let new = table(&[
+        ("b", "UInt32"),
+        ("c", "UInt32"),
+        ("a", "String"),
+        ("e", "IPv4"),
+    ]);
+
+    assert_eq!(
+        changes(&old, &new),
+        [
+            "removed `d` String: breaks deserialization and insertion",
+            "retyped `c` from Nullable(UInt32) to UInt32: breaks deserialization and insertion",
+            "moved `a` from 1 to 3: compatible",
+            "added `e` IPv4: compatible",
+        ]
+    );
+
+    assert_eq!(changes(&old, &old), Vec::<String>::new());
+
+    let diff = ch2rs::diff_tables(&old, &new);
+    assert_eq!(diff[2].kind, ChangeKind::Reordered { from: 1, to: 3 });
+    assert!(diff[0].compatibility.breaks_insertion());
+}
+
+#[test]
+fn types() {
+    let compatibility = |old: &str, new: &str| {
+        let diff = ch2rs::diff_tables(&table(&[("x", old)]), &table(&[("x", new)]));
+        diff.first()
+            .map_or(Compatibility::Compatible, |c| c.compatibility)
+    };
+
+    use Compatibility::*;
+
+    assert_eq!(compatibility("DateTime", "DateTime('UTC')"), Compatible);
+    assert_eq!(compatibility("DateTime64(3)", "DateTime64(6)"), BreaksBoth);
+    assert_eq!(compatibility("UInt32", "UInt64"), BreaksBoth);
+    assert_eq!(
+        compatibility("String", "LowCardinality(String)"),
+        Compatible
+    );
+    assert_eq!(
+        compatibility("Decimal(9, 2)", "Decimal(5, 2)"),
+        BreaksInsertion
+    );
+    assert_eq!(compatibility("Decimal(5, 2)", "Decimal(9, 2)"), Compatible);
+    assert_eq!(compatibility("Decimal(9, 2)", "Decimal(18, 2)"), BreaksBoth);
+
+    let enum_ = "Enum8('a' = 1, 'b' = 2)";
+    assert_eq!(
+        compatibility(enum_, "Enum8('a' = 1, 'b' = 2, 'c' = 3)"),
+        BreaksDeserialization
+    );
+    assert_eq!(compatibility(enum_, "Enum8('a' = 1)"), BreaksInsertion);
+    assert_eq!(compatibility(enum_, "Enum8('x' = 1, 'y' = 2)"), Compatible);
+    assert_eq!(compatibility(enum_, "Enum16('a' = 1, 'b' = 2)"), BreaksBoth);
+    assert_eq!(
+        compatibility(
+            &format!("Array({})", enum_),
+            "Array(Enum8('a' = 1, 'b' = 2, 'c' = 3))"
+        ),
+        BreaksDeserialization
+    );
+    assert_eq!(
+        compatibility(
+            "Map(String, Enum8('a' = 1))",
+            "Map(String, Enum8('a' = 1, 'b' = 2))"
+        ),
+        BreaksDeserialization
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn sources() {
+    let dir = common::temp_path("diff");
+    fs::create_dir_all(&dir).expect("failed to create a directory");
+
+    let sql = fs::read_to_string("tests/schema/events.sql").unwrap();
+    let new = sql.replace("'view' = 2", "'view' = 2, 'buy' = 3");
+    fs::write(dir.join("new.sql"), new).expect("failed to write DDL");
+
+    let options = DiffOptions::from_iter_safe([
+        "ch2rs diff",
+        "events",
+        "ddl:tests/schema/events.sql",
+        &format!("ddl:{}", dir.join("new.sql").display()),
+        "-I",
+        "payload",
+    ])
+    .unwrap();
+    assert_eq!(
+        options.old,
+        SchemaSource::Ddl("tests/schema/events.sql".into())
+    );
+
+    let changes = ch2rs::diff(&options).await.unwrap();
+    fs::remove_dir_all(&dir).expect("failed to remove a directory");
+
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].column, "kind");
+    assert_eq!(
+        changes[0].compatibility,
+        Compatibility::BreaksDeserialization
+    );
+
+    assert_eq!(
+        "server".parse::<SchemaSource>().unwrap(),
+        SchemaSource::Server(None)
+    );
+    assert_eq!(
+        "server:https://ch:8443".parse::<SchemaSource>().unwrap(),
+        SchemaSource::Server(Some("https://ch:8443".into()))
+    );
+    assert!("ddl:".parse::<SchemaSource>().is_err());
+    assert!("events.sql".parse::<SchemaSource>().is_err());
+}
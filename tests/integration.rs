@@ -52,24 +52,57 @@ const CREATE_TABLE_DDL: &str = "
     )
         ENGINE = MergeTree
         ORDER BY u8
+        COMMENT 'all supported types'
 ";
 
+const CREATE_RELATED_DDL: &[&str] = &[
+    "CREATE TABLE ch2rs_test_dist AS ch2rs_test
+        ENGINE = Distributed(test_shard_localhost, currentDatabase(), ch2rs_test)",
+    "CREATE TABLE ch2rs_test_agg (u8 UInt8, cnt UInt64)
+        ENGINE = SummingMergeTree
+        ORDER BY u8",
+    "CREATE MATERIALIZED VIEW ch2rs_test_mv TO ch2rs_test_agg
+        AS SELECT u8, count() AS cnt FROM ch2rs_test GROUP BY u8",
+    "CREATE DICTIONARY ch2rs_test_dict (u8 UInt8, str String, u64 UInt64, f64 Float64)
+        PRIMARY KEY u8, str
+        SOURCE(CLICKHOUSE(TABLE 'ch2rs_test'))
+        LAYOUT(COMPLEX_KEY_HASHED())
+        LIFETIME(0)
+        COMMENT 'bytes by keys'",
+    "SYSTEM RELOAD DICTIONARY ch2rs_test_dict",
+];
+
 async fn recreate_table() {
     let client = Client::default()
         .with_url(URL)
         .with_option("allow_experimental_map_type", "1");
 
     client
-        .query("DROP TABLE IF EXISTS ch2rs_test")
+        .query("DROP DICTIONARY IF EXISTS ch2rs_test_dict")
         .execute()
         .await
-        .expect("failed to drop an old table");
+        .expect("failed to drop an old dictionary");
 
-    client
-        .query(CREATE_TABLE_DDL)
-        .execute()
-        .await
-        .expect("failed to create a table");
+    for table in [
+        "ch2rs_test_mv",
+        "ch2rs_test_agg",
+        "ch2rs_test_dist",
+        "ch2rs_test",
+    ] {
+        client
+            .query(&format!("DROP TABLE IF EXISTS {}", table))
+            .execute()
+            .await
+            .expect("failed to drop an old table");
+    }
+
+    for ddl in [CREATE_TABLE_DDL].iter().chain(CREATE_RELATED_DDL) {
+        client
+            .query(ddl)
+            .execute()
+            .await
+            .expect("failed to create a table");
+    }
 }
 
 async fn run_one(args: Vec<&str>) {
@@ -82,7 +115,7 @@ async fn run_one(args: Vec<&str>) {
 
 async fn generate_all() {
     for t1 in &["-S", "-D", "-SD"] {
-        for t2 in &["--owned", ""] {
+        for t2 in &["--owned", "--string-repr=cow", ""] {
             let args = vec![
                 "ch2rs",
                 "ch2rs_test",
@@ -123,6 +156,69 @@ async fn generate_all() {
     }
 }
 
+const QUERY: &str = "
+    SELECT u8, count() AS cnt, max(dt) AS last, groupArray(str) AS strs
+      FROM ch2rs_test
+     WHERE dt >= {from:DateTime} AND str NOT IN ({exclude:String}, '{not:Param}')
+       AND dt < {from:DateTime} + 3600
+       AND enum8 != {kind:Enum8('' = -128, 'Foo Bar' = 0)}
+     GROUP BY u8
+";
+
+async fn generate_query() {
+    let args = vec![
+        "ch2rs",
+        "-U",
+        URL,
+        "-D",
+        "--query",
+        QUERY,
+        "-T",
+        "DateTime=u32",
+    ];
+
+    let options = ch2rs::Options::from_iter(args);
+    let code = ch2rs::generate(options)
+        .await
+        .expect("failed to generate a struct");
+
+    let mut settings = insta::Settings::clone_current();
+    settings.set_prepend_module_to_snapshot(false);
+    settings.bind(|| insta::assert_snapshot!("query", code));
+}
+
+async fn check_distributed() {
+    let options = ch2rs::Options::from_iter(["ch2rs", "ch2rs_test_dist", "-U", URL]);
+    let table = ch2rs::mine(&options)
+        .await
+        .expect("failed to mine a Distributed table");
+    assert_eq!(table.engine, "Distributed");
+}
+
+async fn generate_mv() {
+    let options =
+        ch2rs::Options::from_iter(["ch2rs", "ch2rs_test_mv", "-U", URL, "-D", "--follow-mv"]);
+    let code = ch2rs::generate(options)
+        .await
+        .expect("failed to generate a struct");
+
+    let mut settings = insta::Settings::clone_current();
+    settings.set_prepend_module_to_snapshot(false);
+    settings.bind(|| insta::assert_snapshot!("mv", code));
+}
+
+async fn generate_dictionary() {
+    let options =
+        ch2rs::Options::from_iter(["ch2rs", "ch2rs_test_dict", "-U", URL, "-D", "--dictionary"]);
+    let code = ch2rs::generate(options)
+        .await
+        .expect("failed to generate a struct");
+
+    let mut settings = insta::Settings::clone_current();
+    settings.set_prepend_module_to_snapshot(false);
+    settings.bind(|| insta::assert_snapshot!("dictionary", code));
+}
+
 fn extract_code_from_snapshots() {
     match fs::remove_dir_all("target/snapshots") {
         Ok(_) => {}
@@ -157,6 +253,10 @@ fn compile_snapshots() {
 async fn all() {
     recreate_table().await;
     generate_all().await;
+    generate_query().await;
+    check_distributed().await;
+    generate_mv().await;
+    generate_dictionary().await;
     extract_code_from_snapshots();
     compile_snapshots();
 }
@@ -0,0 +1,152 @@
+use std::{env, fs, process::Command};
+
+use ch2rs::{
+    schema::{Column, SqlType, Table},
+    Options, StringRepr,
+};
+
+mod common;
+
+#[test]
+fn generate_from_table() {
+    let table = Table::new(
+        "default",
+        "events",
+        [
+            Column::new("id", SqlType::UInt64),
+            Column::new("kind", ch2rs::parse_type("LowCardinality(String)").unwrap()),
+        ],
+    );
+
+    let options = Options {
+        deserialize: true,
+        owned: true,
+        ..Options::default()
+    };
+
+    let code = ch2rs::generate_from_table(&table, &options).expect("failed to generate");
+    assert!(code.contains("pub struct Row {"));
+    assert!(code.contains("pub id: u64,"));
+    assert!(code.contains("pub kind: String,"));
+    assert!(code.contains(r#"pub const TABLE: &'static str = "events";"#));
+}
+
+// Generated files must not break `cargo fmt --check` of users' crates.
+#[test]
+fn passes_rustfmt() {
+    let wide = Table::new(
+        "default",
+        "events",
+        [
+            Column::new(
+                "a_rather_long_column_name_with_a_nested_map",
+                ch2rs::parse_type("Map(String, Tuple(String, String, Array(String)))").unwrap(),
+            ),
+            Column::new(
+                "wide_tuple",
+                ch2rs::parse_type("Tuple(String, String, String, String, String, String, String)")
+                    .unwrap(),
+            ),
+        ],
+    );
+
+    // Constants of query helpers don't fit on one line.
+    let long = Table::new(
+        "a_rather_long_database_name",
+        "a_rather_long_table_name_to_wrap_constants",
+        [Column::new(
+            "id",
+            ch2rs::parse_type("Array(Tuple(String, Tuple(UInt64, UInt64)))").unwrap(),
+        )],
+    );
+
+    let path = common::temp_path("rustfmt").with_extension("rs");
+
+    let mut cases = [StringRepr::Str, StringRepr::String, StringRepr::Cow]
+        .map(|string_repr| Options {
+            deserialize: true,
+            string_repr,
+            ..Options::default()
+        })
+        .to_vec();
+
+    cases.push(Options {
+        serialize: true,
+        deserialize: true,
+        query_helpers: true,
+        inserter: true,
+        verify_schema: true,
+        struct_name: Some("ARatherLongStructNameToWrapSignaturesOfHelpers".into()),
+        // Short names share lines.
+        ignore: (0..30).map(|i| format!("c{}", i)).collect(),
+        ..Options::default()
+    });
+
+    // Derives on one line, with all paths on the next line and vertically.
+    for count in [1, 4, 8] {
+        cases.push(Options {
+            serialize: true,
+            deserialize: true,
+            derives: (0..count).map(|i| format!("some::Derive{}", i)).collect(),
+            ..Options::default()
+        });
+    }
+
+    for table in [&wide, &long] {
+        for options in &cases {
+            let code = ch2rs::generate_from_table(table, options).expect("failed to generate");
+            fs::write(&path, format!("{}\n", code)).expect("failed to write the code");
+
+            let output = Command::new("rustfmt")
+                .args(["--edition", "2021", "--check"])
+                .arg(&path)
+                .output();
+
+            // rustfmt isn't installed everywhere.
+            let Ok(output) = output else {
+                break;
+            };
+            let diff = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "isn't formatted:\n{}", diff);
+        }
+    }
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn display_types() {
+    for raw in [
+        "UInt8",
+        "FixedString(16)",
+        "DateTime('Europe/Moscow')",
+        "DateTime64(9, 'UTC')",
+        "Decimal(18, 9)",
+        "Enum8('' = -128, 'Foo Bar' = 0)",
+        "Array(Nullable(String))",
+        "Map(String, Array(UInt32))",
+    ] {
+        let type_ = ch2rs::parse_type(raw).expect("failed to parse");
+        assert_eq!(type_.to_string(), raw);
+    }
+}
+
+#[test]
+fn parse_aliases() {
+    for (alias, expected) in [
+        ("INT", "Int32"),
+        ("bigint unsigned", "UInt64"),
+        ("VARCHAR(255)", "String"),
+        ("BOOLEAN", "Bool"),
+        ("DOUBLE PRECISION", "Float64"),
+        ("NUMERIC(10, 2)", "Decimal(10, 2)"),
+        ("Decimal64(4)", "Decimal(18, 4)"),
+        (
+            "Tuple(a String, b Map(String, UInt8))",
+            "Tuple(String, Map(String, UInt8))",
+        ),
+    ] {
+        let type_ = ch2rs::parse_type(alias).expect("failed to parse");
+        assert_eq!(type_.to_string(), expected);
+    }
+}
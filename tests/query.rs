@@ -0,0 +1,47 @@
+use ch2rs::Options;
+
+async fn generate(query: &str) -> String {
+    let options = Options {
+        query: Some(query.into()),
+        connection: ch2rs::Connection {
+            url: Some("http://127.0.0.1:1".into()),
+            ..Default::default()
+        },
+        ..Options::default()
+    };
+
+    let err = ch2rs::generate(options).await.unwrap_err();
+    format!("{:#}", err)
+}
+
+// Parameters are checked before connecting, so no server is required.
+#[tokio::test(flavor = "current_thread")]
+async fn params() {
+    let err = generate("SELECT * FROM {table:Identifier} WHERE id = {id:UInt64}").await;
+    assert!(
+        err.starts_with("failed to handle the query: failed to describe the query"),
+        "{}",
+        err
+    );
+
+    let err = generate("SELECT {table:Identifier}, {userId:UInt64}, {user_id:UInt64}").await;
+    assert_eq!(
+        err,
+        "failed to handle the query: failed to parse parameters: \
+         the `userId` and `user_id` parameters are both named `user_id` in Rust"
+    );
+
+    // Types without a default impl require `-T`, the row's columns would as well.
+    let err = generate("SELECT {from:DateTime}, {id:UInt64}").await;
+    assert_eq!(
+        err,
+        "failed to handle the query: failed to generate the `from` param of type `DateTime`: \
+         there is no default impl for DateTime, use -T or -O to specify it"
+    );
+
+    let err = generate("SELECT {day:Date}").await;
+    assert!(err.ends_with("there is no default impl for Date, use -T or -O to specify it"));
+
+    let err = generate("SELECT {id:UInt64} + {id:UInt32}").await;
+    assert!(err.ends_with("the `id` parameter is used with different types: `UInt64` and `UInt32`"));
+}
@@ -0,0 +1,24 @@
+-- Types are written in the way people usually write them by hand.
+CREATE TABLE IF NOT EXISTS analytics.sessions ON CLUSTER main
+(
+    id          BIGINT UNSIGNED,
+    user_id     INT NOT NULL,
+    started_at  TIMESTAMP DEFAULT now() COMMENT 'when the session started',
+    duration    DOUBLE PRECISION CODEC(Gorilla, ZSTD(3)),
+    country     VARCHAR(2) NULL,
+    is_bot      BOOLEAN MATERIALIZED user_id = 0,
+    revenue     NUMERIC(18, 4) DEFAULT 0,
+    attempts    DEFAULT 1,
+    raw_user_id ALIAS user_id,
+    referer     TEXT EPHEMERAL,
+    tags        Tuple(name String, score Float32),
+
+    INDEX idx_country country TYPE set(100) GRANULARITY 4
+)
+ENGINE = ReplacingMergeTree(started_at)
+PARTITION BY toYYYYMM(started_at)
+ORDER BY (user_id, id)
+SETTINGS index_granularity = 8192
+COMMENT 'user sessions';
+
+CREATE TABLE untyped (value DEFAULT rand()) ENGINE = Memory;
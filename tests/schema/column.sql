@@ -0,0 +1,9 @@
+-- Names clashing with metadata of columns.
+CREATE TABLE column
+(
+    column      Enum8('a' = 1, 'b' = 2),
+    ch2rs       String,
+    timestamp   DateTime
+)
+ENGINE = MergeTree
+ORDER BY timestamp;
@@ -0,0 +1,3 @@
+-- The same table in different databases.
+CREATE TABLE db1.events (id UInt8) ENGINE = Memory;
+CREATE TABLE analytics.events (kind String) ENGINE = Memory;
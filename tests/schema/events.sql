@@ -0,0 +1,9 @@
+CREATE TABLE events
+(
+    timestamp DateTime COMMENT 'when the event happened',
+    kind      Enum8('click' = 1, 'view' = 2),
+    user      String,
+    payload   String
+)
+ENGINE = MergeTree
+ORDER BY (kind, timestamp);
@@ -0,0 +1,10 @@
+-- `events.sql` after a migration.
+CREATE TABLE events
+(
+    timestamp DateTime('UTC') COMMENT 'when the event happened',
+    kind      Enum8('click' = 1, 'view' = 2),
+    user      String,
+    ip        IPv4 COMMENT 'the client\'s address'
+)
+ENGINE = MergeTree
+ORDER BY (kind, timestamp);
@@ -0,0 +1,7 @@
+-- Names requiring quotes: a leading digit, a dash and reserved words.
+CREATE TABLE `order`.`1st-events`
+(
+    id       UInt64,
+    `select` String
+)
+ENGINE = Memory;
@@ -0,0 +1,44 @@
+use std::fs;
+
+use ch2rs::{Options, Snapshot};
+
+mod common;
+
+const PATH: &str = "tests/schema/snapshot.json";
+
+#[tokio::test(flavor = "current_thread")]
+async fn generate_from_snapshot() {
+    let options = Options {
+        database: "analytics".into(),
+        ddl: None,
+        schema_file: Some(PATH.into()),
+        owned: true,
+        ignore: vec!["payload".into()],
+        ..common::options("events")
+    };
+
+    let code = ch2rs::generate(options).await.expect("failed to generate");
+
+    assert!(code.contains("ch2rs events --schema-file tests/schema/snapshot.json -d analytics"));
+    assert!(code.contains("/// * Sorting key: `kind, timestamp`"));
+    assert!(code.contains("pub timestamp: u32,"));
+    assert!(code.contains("pub kind: Kind,"));
+    assert!(code.contains("pub user: String,"));
+    assert!(!code.contains("pub payload"));
+}
+
+#[test]
+fn stable_format() {
+    let json = fs::read_to_string(PATH).expect("failed to read the snapshot");
+    let snapshot = Snapshot::from_json(&json).expect("failed to parse the snapshot");
+    assert_eq!(snapshot.to_json(), json);
+}
+
+#[test]
+fn unsupported_version() {
+    let err = Snapshot::from_json(r#"{"version": 2, "tables": []}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported snapshot version 2, expected 1"
+    );
+}
@@ -0,0 +1,117 @@
+use std::{env, fs};
+
+use ch2rs::{Options, SchemaSource, Target, Watcher, WriteStatus};
+use structopt::StructOpt;
+
+mod common;
+
+#[tokio::test(flavor = "current_thread")]
+async fn poll() {
+    let dir = common::temp_path("watch");
+    fs::create_dir_all(&dir).expect("failed to create a directory");
+
+    let ddl = dir.join("events.sql");
+    fs::copy("tests/schema/events.sql", &ddl).expect("failed to copy");
+
+    let target = Target {
+        output: dir.join("events.rs"),
+        options: Options {
+            table: "events".into(),
+            ddl: Some(ddl.clone()),
+            ignore: vec!["timestamp".into()],
+            ..Options::default()
+        },
+    };
+
+    let mut watcher = Watcher::new(vec![target]).unwrap();
+    let statuses = |results: Vec<(&Target, anyhow::Result<WriteStatus>)>| {
+        results
+            .into_iter()
+            .map(|(_, status)| status.map_err(|err| format!("{:#}", err)))
+            .collect::<Vec<_>>()
+    };
+
+    // Everything is generated on the first poll.
+    assert_eq!(statuses(watcher.poll().await), [Ok(WriteStatus::Created)]);
+    assert_eq!(statuses(watcher.poll().await), []);
+
+    // The schema is changed, even within the same second.
+    let sql = fs::read_to_string(&ddl).unwrap();
+    fs::write(
+        &ddl,
+        sql.replace("payload   String", "payload   String,\n    ip IPv4"),
+    )
+    .unwrap();
+
+    assert_eq!(statuses(watcher.poll().await), [Ok(WriteStatus::Updated)]);
+    let code = fs::read_to_string(dir.join("events.rs")).unwrap();
+    assert!(code.contains("pub ip: ::std::net::Ipv4Addr,"), "{}", code);
+
+    // Errors are reported once, but the target is retried until it succeeds.
+    fs::remove_file(&ddl).unwrap();
+    let results = statuses(watcher.poll().await);
+    assert!(matches!(&results[..], [Err(err)] if err.contains("failed to read")));
+    assert_eq!(statuses(watcher.poll().await), []);
+
+    fs::write(&ddl, &sql).unwrap();
+    assert_eq!(statuses(watcher.poll().await), [Ok(WriteStatus::Updated)]);
+    assert_eq!(statuses(watcher.poll().await), []);
+
+    // The other version of `--compatible-with` is tracked as well.
+    let other = dir.join("events_v2.sql");
+    fs::write(&other, &sql).unwrap();
+    let target = Target {
+        output: dir.join("compatible.rs"),
+        options: Options {
+            table: "events".into(),
+            ddl: Some(ddl.clone()),
+            compatible_with: Some(SchemaSource::Ddl(other.clone())),
+            ignore: vec!["timestamp".into(), "ip".into()],
+            ..Options::default()
+        },
+    };
+
+    let mut watcher = Watcher::new(vec![target]).unwrap();
+    assert_eq!(statuses(watcher.poll().await), [Ok(WriteStatus::Created)]);
+    fs::write(
+        &other,
+        sql.replace("payload   String", "payload   String,\n    ip IPv4"),
+    )
+    .unwrap();
+    assert_eq!(statuses(watcher.poll().await), [Ok(WriteStatus::Updated)]);
+    assert_eq!(statuses(watcher.poll().await), []);
+
+    fs::remove_dir_all(&dir).expect("failed to remove a directory");
+
+    let stdin = Target {
+        output: "events.rs".into(),
+        options: Options {
+            table: "events".into(),
+            ddl: Some("-".into()),
+            ..Options::default()
+        },
+    };
+
+    let err = Watcher::new(vec![stdin]).err().unwrap();
+    assert_eq!(
+        format!("{:#}", err),
+        "failed to watch events.rs: DDL can't be read from stdin in the watch mode"
+    );
+}
+
+#[test]
+fn interval() {
+    let parse = |interval: &str| {
+        let args = ["ch2rs", "--watch", "--interval", interval];
+        Options::from_iter_safe(args).map(|options| options.interval)
+    };
+
+    assert_eq!(parse("5").unwrap(), 5);
+    let err = parse("0").unwrap_err();
+    assert!(
+        err.message
+            .contains("the interval must be at least 1 second"),
+        "{}",
+        err
+    );
+}